@@ -0,0 +1,103 @@
+//! Times how long `Deconz` takes to read a stream of frames from the adapter, both when the serial
+//! port returns them in large chunks and when it returns a byte per read (as the reader used to
+//! request):
+//!
+//!     cargo bench -p deconz --bench read_frames
+
+use std::io::Cursor;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use deconz::{Deconz, DeconzConfig, Metrics};
+use tokio::io::AsyncRead;
+use tokio::sync::oneshot;
+
+const FRAMES: usize = 1000;
+const ITERATIONS: u32 = 100;
+
+/// A DeviceStateChanged frame (connected, with free slots), with its checksum and SLIP framing.
+const FRAME: [u8; 10] = [0xC0, 0x0E, 0x00, 0x00, 0x06, 0x00, 0x22, 0xCA, 0xFF, 0xC0];
+
+/// Reports when the last of the frames has been read.
+struct FrameCounter {
+    received: AtomicUsize,
+    done: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl Metrics for FrameCounter {
+    fn frame_received(&self) {
+        if self.received.fetch_add(1, Ordering::SeqCst) + 1 == FRAMES {
+            if let Some(done) = self.done.lock().expect("poisoned").take() {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+/// Returns at most one byte from each read, like a serial port that is read as soon as each byte
+/// arrives.
+struct OneByteAtATime<R>(R);
+
+impl<R> AsyncRead for OneByteAtATime<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let len = buf.len().min(1);
+        Pin::new(&mut self.0).poll_read(cx, &mut buf[..len])
+    }
+}
+
+/// Reads `FRAMES` frames from `reader`, returning how long it took.
+async fn read_frames<R>(reader: R) -> Duration
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let (done, finished) = oneshot::channel();
+    let config = DeconzConfig {
+        metrics: Arc::new(FrameCounter {
+            received: AtomicUsize::new(0),
+            done: Mutex::new(Some(done)),
+        }),
+        ..DeconzConfig::default()
+    };
+
+    let start = Instant::now();
+    let (_deconz, _aps_reader) = Deconz::with_config(reader, tokio::io::sink(), config);
+    finished
+        .await
+        .expect("stopped reading before the last frame");
+    start.elapsed()
+}
+
+fn frames() -> Cursor<Vec<u8>> {
+    Cursor::new(FRAME.repeat(FRAMES))
+}
+
+#[tokio::main]
+async fn main() {
+    let mut chunked = Duration::default();
+    let mut one_byte = Duration::default();
+    for _ in 0..ITERATIONS {
+        chunked += read_frames(frames()).await;
+        one_byte += read_frames(OneByteAtATime(frames())).await;
+    }
+
+    println!(
+        "chunked reads:       {:?} per {} frames",
+        chunked / ITERATIONS,
+        FRAMES
+    );
+    println!(
+        "one byte per read:   {:?} per {} frames",
+        one_byte / ITERATIONS,
+        FRAMES
+    );
+}
@@ -0,0 +1,22 @@
+//! Connects to an adapter exposed over TCP by a serial bridge (e.g. ser2net), rather than a local
+//! serial port:
+//!
+//!     cargo run --example tcp -- 192.168.1.2:5000
+
+use deconz::Deconz;
+use tokio::net::TcpStream;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = std::env::args().nth(1).ok_or("usage: tcp <host:port>")?;
+
+    let stream = TcpStream::connect(addr).await?;
+    let (reader, writer) = tokio::io::split(stream);
+    let (deconz, _aps_reader) = Deconz::new(reader, writer);
+
+    let (version, platform) = deconz.version().await?;
+    println!("version = {:?}, platform = {:?}", version, platform);
+    println!("device state = {:?}", deconz.device_state().await?);
+
+    Ok(())
+}
@@ -1,11 +1,17 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
 use tokio::stream::Stream;
-use tokio::sync::{mpsc, oneshot, watch};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 use tophamm_helpers::awaiting;
+#[cfg(feature = "tracing")]
+use tracing::{debug, debug_span, error, warn, Instrument};
 
 use crate::protocol::RequestId;
 use crate::{
-    ApsDataConfirm, ApsDataIndication, ApsDataRequest, Deconz, DeviceState, Error, ErrorKind,
-    Request, Response, Result,
+    ApsDataConfirm, ApsDataIndication, ApsDataRequest, Deconz, DeviceState, Endpoint, Error,
+    ErrorKind, Metrics, Request, Response, Result,
 };
 
 pub type Awaiting = awaiting::Awaiting<RequestId, ApsDataConfirm, Error>;
@@ -27,27 +33,65 @@ pub struct ApsRequests {
 
 impl ApsRequests {
     pub async fn task(mut self) -> Result<()> {
-        // Wait until the device tells us that it's ready to receive requests.
-        let mut request_free_slots = false;
+        // Wait until the device tells us that it's ready to receive requests. The first
+        // DeviceState may be a placeholder from before the adapter has reported anything, so don't
+        // take its network state to mean that we're offline.
+        let mut request_free_slots = match self.device_state.recv().await {
+            Some(device_state) => device_state.data_request_free_slots,
+            None => return Ok(()),
+        };
+        // The next request to forward, once there's a free slot.
+        let mut pending: Option<ApsRequest> = None;
 
         loop {
             tokio::select! {
                 Some(device_state) = self.device_state.recv() => {
                     request_free_slots = device_state.data_request_free_slots;
+
+                    // No slots will come free and no confirms will arrive until the network is
+                    // back, so don't leave anyone waiting for them.
+                    if !device_state.network_state.is_operational() {
+                        if let Some((_, _, sender)) = pending.take() {
+                            let _ = sender.send(Err(ErrorKind::NotConnected.into()));
+                        }
+                        self.awaiting.cancel_all(|| ErrorKind::NotConnected.into());
+                    }
                 }
-                Some((id, request, sender)) = self.requests.recv(),
-                    if request_free_slots =>
-                {
-                    // Assume we can only send one message at a time. We'll get a DeviceState in
-                    // the response which will tell us if we can send more.
-                    request_free_slots = false;
-
-                    let awaiting = self.awaiting.clone();
-                    let future = self.forward_request(id, request);
-                    awaiting.register_while(id, sender, future).await;
+                Some((id, request, sender)) = self.requests.recv(), if pending.is_none() => {
+                    // The adapter only reports its state alongside other responses, so ask
+                    // whether the network is up rather than queueing the request until it (maybe)
+                    // comes back.
+                    if !request_free_slots {
+                        match self.deconz.device_state().await {
+                            Ok(device_state) if !device_state.network_state.is_operational() => {
+                                let _ = sender.send(Err(ErrorKind::NotConnected.into()));
+                                continue;
+                            }
+                            Ok(device_state) => {
+                                request_free_slots = device_state.data_request_free_slots;
+                            }
+                            Err(error) => error!("device_state: {}", error),
+                        }
+                    }
+                    pending = Some((id, request, sender));
                 }
                 else => break,
             }
+
+            if !request_free_slots {
+                continue;
+            }
+            if let Some((id, request, sender)) = pending.take() {
+                // Assume we can only send one message at a time. We'll get a DeviceState in the
+                // response which will tell us if we can send more.
+                request_free_slots = false;
+
+                let awaiting = self.awaiting.clone();
+                let future = self.forward_request(id, request);
+                #[cfg(feature = "tracing")]
+                let future = future.instrument(debug_span!("aps_request", request_id = id));
+                awaiting.register_while(id, sender, future).await;
+            }
         }
 
         Ok(())
@@ -77,6 +121,7 @@ pub struct ApsConfirms {
     pub deconz: Deconz,
     pub device_state: watch::Receiver<DeviceState>,
     pub awaiting: Awaiting,
+    pub confirms: broadcast::Sender<(RequestId, ApsDataConfirm)>,
 }
 
 impl ApsConfirms {
@@ -102,6 +147,13 @@ impl ApsConfirms {
             resp => return Err(ErrorKind::UnexpectedResponse(resp.command_id()).into()),
         };
 
+        #[cfg(feature = "tracing")]
+        let _span = debug_span!("aps_confirm", request_id).entered();
+        debug!("received confirm = {:?}", aps_data_confirm);
+
+        // There may be no one observing confirms, which is fine.
+        let _ = self.confirms.send((request_id, aps_data_confirm.clone()));
+
         if let Some(_) = self.awaiting.send(&request_id, Ok(aps_data_confirm)) {
             return Err(ErrorKind::UnsolicitedConfirm(request_id).into());
         }
@@ -112,10 +164,16 @@ impl ApsConfirms {
 
 /// Task responsible for querying the adapter for ApsDataIndications and forwarding to the
 /// application.
+///
+/// Indications are never awaited into an `ApsReader`: if its queue is full, the indication is
+/// dropped. Otherwise a slow reader would stop this task from draining the adapter, which would
+/// eventually run out of buffers and stop accepting requests.
 pub struct ApsIndications {
     pub deconz: Deconz,
     pub device_state: watch::Receiver<DeviceState>,
     pub aps_data_indications: mpsc::Sender<ApsDataIndication>,
+    pub endpoints: EndpointRegistry,
+    pub metrics: Arc<dyn Metrics>,
 }
 
 impl ApsIndications {
@@ -130,15 +188,58 @@ impl ApsIndications {
                     }
                 };
 
-                if let Err(_) = self.aps_data_indications.send(aps_data_indication).await {
+                let aps_data_indication = match self.forward_to_endpoint(aps_data_indication) {
+                    Some(aps_data_indication) => aps_data_indication,
+                    None => continue,
+                };
+
+                match self.aps_data_indications.try_send(aps_data_indication) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => self.dropped(None),
                     // The receiver has been dropped - no point continuing.
-                    break;
+                    Err(TrySendError::Closed(_)) => break,
                 }
             }
         }
         Ok(())
     }
 
+    /// Send the indication to the reader registered for its destination endpoint, if any.
+    /// Otherwise, returns it so that it can be sent to the default reader.
+    fn forward_to_endpoint(
+        &mut self,
+        aps_data_indication: ApsDataIndication,
+    ) -> Option<ApsDataIndication> {
+        let endpoint = aps_data_indication.destination_endpoint;
+        let mut sender = match self.endpoints.sender(&endpoint) {
+            Some(sender) => sender,
+            None => return Some(aps_data_indication),
+        };
+
+        match sender.try_send(aps_data_indication) {
+            Ok(()) => None,
+            Err(TrySendError::Full(_)) => {
+                self.dropped(Some(endpoint));
+                None
+            }
+            // The reader is being dropped, which releases the endpoint.
+            Err(TrySendError::Closed(aps_data_indication)) => Some(aps_data_indication),
+        }
+    }
+
+    /// Records an indication dropped because the reader for `endpoint` (or the default reader, if
+    /// `None`) is full.
+    fn dropped(&self, endpoint: Option<Endpoint>) {
+        match endpoint {
+            Some(endpoint) => warn!(
+                "dropping aps_data_indication: reader for {:?} is full",
+                endpoint
+            ),
+            None => warn!("dropping aps_data_indication: reader is full"),
+        }
+        self.metrics.indication_dropped();
+    }
+
     async fn aps_data_indication(&mut self) -> Result<ApsDataIndication> {
         let response = self.deconz.make_request(Request::ApsDataIndication).await?;
         let aps_data_indication = match response {
@@ -152,8 +253,131 @@ impl ApsIndications {
     }
 }
 
+/// The first and last endpoints that applications may use. Endpoint 0 is the ZDO, and those
+/// above 240 are reserved.
+const FIRST_ENDPOINT: u8 = 1;
+const LAST_ENDPOINT: u8 = 240;
+
+/// Keeps track of which endpoints have their own `ApsReader`, so that indications can be
+/// demultiplexed by destination endpoint.
+#[derive(Clone)]
+pub struct EndpointRegistry {
+    senders: Arc<Mutex<HashMap<Endpoint, mpsc::Sender<ApsDataIndication>>>>,
+    capacity: usize,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl EndpointRegistry {
+    pub fn new(capacity: usize, metrics: Arc<dyn Metrics>) -> Self {
+        Self {
+            senders: Default::default(),
+            capacity,
+            metrics,
+        }
+    }
+
+    pub fn register(&self, endpoint: Endpoint) -> Result<ApsReader> {
+        let mut senders = self.senders.lock().expect("poisoned");
+        if senders.contains_key(&endpoint) {
+            return Err(ErrorKind::EndpointInUse(endpoint).into());
+        }
+
+        let (tx, rx) = mpsc::channel(self.capacity);
+        senders.insert(endpoint, tx);
+        Ok(self.reader(endpoint, rx))
+    }
+
+    pub fn allocate(&self) -> Result<(Endpoint, ApsReader)> {
+        let mut senders = self.senders.lock().expect("poisoned");
+        let endpoint = (FIRST_ENDPOINT..=LAST_ENDPOINT)
+            .map(Endpoint)
+            .find(|endpoint| !senders.contains_key(endpoint))
+            .ok_or(ErrorKind::NoFreeEndpoints)?;
+
+        let (tx, rx) = mpsc::channel(self.capacity);
+        senders.insert(endpoint, tx);
+        Ok((endpoint, self.reader(endpoint, rx)))
+    }
+
+    /// A reader that releases `endpoint` when dropped.
+    fn reader(&self, endpoint: Endpoint, rx: mpsc::Receiver<ApsDataIndication>) -> ApsReader {
+        let mut reader = ApsReader::new(rx, self.capacity, self.metrics.clone());
+        reader.registration = Some((self.clone(), endpoint));
+        reader
+    }
+
+    fn sender(&self, endpoint: &Endpoint) -> Option<mpsc::Sender<ApsDataIndication>> {
+        self.senders
+            .lock()
+            .expect("poisoned")
+            .get(endpoint)
+            .cloned()
+    }
+
+    fn deregister(&self, endpoint: &Endpoint) {
+        self.senders.lock().expect("poisoned").remove(endpoint);
+    }
+}
+
 pub struct ApsReader {
-    pub(crate) rx: mpsc::Receiver<ApsDataIndication>,
+    rx: mpsc::Receiver<ApsDataIndication>,
+    /// The endpoint this reader was registered for, if any.
+    registration: Option<(EndpointRegistry, Endpoint)>,
+    /// The depth of the queues of the readers created by `partition`.
+    capacity: usize,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl ApsReader {
+    pub(crate) fn new(
+        rx: mpsc::Receiver<ApsDataIndication>,
+        capacity: usize,
+        metrics: Arc<dyn Metrics>,
+    ) -> Self {
+        Self {
+            rx,
+            registration: None,
+            capacity,
+            metrics,
+        }
+    }
+
+    /// Splits the reader in two: indications for which `predicate` returns true are delivered to
+    /// the first reader, and all others to the second. Indications for a reader that has been
+    /// dropped are discarded, as are those for a reader whose queue is full, so that a slow reader
+    /// doesn't hold up the other.
+    pub fn partition<F>(mut self, predicate: F) -> (ApsReader, ApsReader)
+    where
+        F: Fn(&ApsDataIndication) -> bool + Send + 'static,
+    {
+        let (mut matching_tx, matching_rx) = mpsc::channel(self.capacity);
+        let (mut others_tx, others_rx) = mpsc::channel(self.capacity);
+        let matching = ApsReader::new(matching_rx, self.capacity, self.metrics.clone());
+        let others = ApsReader::new(others_rx, self.capacity, self.metrics.clone());
+
+        tokio::spawn(async move {
+            while let Some(aps_data_indication) = self.rx.recv().await {
+                let tx = match predicate(&aps_data_indication) {
+                    true => &mut matching_tx,
+                    false => &mut others_tx,
+                };
+                if let Err(TrySendError::Full(_)) = tx.try_send(aps_data_indication) {
+                    warn!("dropping aps_data_indication: partitioned reader is full");
+                    self.metrics.indication_dropped();
+                }
+            }
+        });
+
+        (matching, others)
+    }
+}
+
+impl Drop for ApsReader {
+    fn drop(&mut self) {
+        if let Some((endpoints, endpoint)) = self.registration.take() {
+            endpoints.deregister(&endpoint);
+        }
+    }
 }
 
 impl Stream for ApsReader {
@@ -166,3 +390,71 @@ impl Stream for ApsReader {
         self.rx.poll_recv(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::stream::StreamExt;
+
+    use crate::{
+        ClusterId, DestinationAddress, ExtendedAddress, NoMetrics, ProfileId, ShortAddress,
+        SourceAddress,
+    };
+
+    fn indication(destination_endpoint: u8) -> ApsDataIndication {
+        ApsDataIndication {
+            destination_address: DestinationAddress::Nwk(ShortAddress(0x0000)),
+            destination_endpoint: Endpoint(destination_endpoint),
+            source_address: SourceAddress {
+                short: ShortAddress(0x1234),
+                extended: ExtendedAddress(0x00212effff012345),
+            },
+            source_endpoint: Endpoint(1),
+            profile_id: ProfileId::HA,
+            cluster_id: ClusterId(0x0006),
+            asdu: vec![0x18, 0x01, 0x0b, 0x00, 0x00],
+        }
+    }
+
+    #[test]
+    fn endpoint_released_when_reader_dropped() {
+        let endpoints = EndpointRegistry::new(1, Arc::new(NoMetrics));
+
+        let reader = endpoints.register(Endpoint(1)).unwrap();
+        assert!(endpoints.register(Endpoint(1)).is_err());
+
+        drop(reader);
+        endpoints.register(Endpoint(1)).unwrap();
+    }
+
+    #[test]
+    fn allocated_endpoint_released_when_reader_dropped() {
+        let endpoints = EndpointRegistry::new(1, Arc::new(NoMetrics));
+
+        let (endpoint, reader) = endpoints.allocate().unwrap();
+        assert_eq!(endpoint, Endpoint(FIRST_ENDPOINT));
+        drop(reader);
+
+        let (endpoint, _reader) = endpoints.allocate().unwrap();
+        assert_eq!(endpoint, Endpoint(FIRST_ENDPOINT));
+    }
+
+    #[tokio::test]
+    async fn partition_drops_indications_for_a_full_reader() {
+        let (mut tx, rx) = mpsc::channel(1);
+        let reader = ApsReader::new(rx, 1, Arc::new(NoMetrics));
+        let (mut matching, mut others) = reader.partition(|i| i.destination_endpoint.0 == 1);
+
+        // Nothing reads from `matching`, which mustn't stop `others` receiving indications.
+        for _ in 0..3 {
+            tx.send(indication(1)).await.unwrap();
+        }
+        tx.send(indication(2)).await.unwrap();
+        drop(tx);
+
+        assert_eq!(others.next().await, Some(indication(2)));
+        assert_eq!(others.next().await, None);
+        assert_eq!(matching.next().await, Some(indication(1)));
+        assert_eq!(matching.next().await, None);
+    }
+}
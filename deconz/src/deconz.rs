@@ -1,71 +1,202 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+use futures_util::sink::Sink;
+use futures_util::stream::FuturesUnordered;
 use tokio::io::{AsyncRead, AsyncWrite};
-use tokio::sync::{mpsc, oneshot, watch};
+use tokio::stream::{Stream, StreamExt};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 use tophamm_helpers::{awaiting, IncrementingId};
+#[cfg(feature = "tracing")]
+use tracing::{debug, debug_span, error, info, warn, Instrument};
 
-use crate::aps::{self, ApsConfirms, ApsIndications, ApsReader, ApsRequest, ApsRequests};
+use crate::aps::{
+    self, ApsConfirms, ApsIndications, ApsReader, ApsRequest, ApsRequests, EndpointRegistry,
+};
 use crate::protocol::RequestId;
-use crate::slip;
+use crate::slip::{self, Checksum, SlipError};
 use crate::{
-    ApsDataConfirm, ApsDataRequest, DeviceState, Error, ErrorKind, Platform, Request, Response,
-    Result, SequenceId, Version,
+    ApsDataConfirm, ApsDataRequest, CommandId, ConfirmStatus, DeviceState, Endpoint, Error,
+    ErrorKind, Metrics, NetworkState, NetworkStateChange, NoMetrics, Parameter, ParameterId,
+    Platform, ReadWire, Request, Response, Result, SequenceId, ShortAddress, Version,
 };
 
-/// A command from Deconz to the Tx task, representing a serial Request to be made and the channel
-/// tha the response should be sent on.
-type SerialCommand = (SequenceId, Request, oneshot::Sender<Result<Response>>);
+/// A command from Deconz to the Tx task, representing a serial Request to be made, the channel
+/// tha the response should be sent on and the span of the `make_request` that sent it.
+type SerialCommand = (
+    SequenceId,
+    Request,
+    oneshot::Sender<Result<Response>>,
+    RequestSpan,
+);
+
+/// The span that logging about a request (and its response) belongs to. Nothing without the
+/// tracing feature.
+#[cfg(feature = "tracing")]
+type RequestSpan = tracing::Span;
+#[cfg(not(feature = "tracing"))]
+type RequestSpan = ();
+
+/// The spans of the requests awaiting a response, so that Rx can log within them.
+type Spans = Arc<Mutex<HashMap<SequenceId, RequestSpan>>>;
 
 type Awaiting = awaiting::Awaiting<SequenceId, Response, Error>;
 
 /// Wait for a response to serial commands for at most this amount of time.
 const TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Give up on a partially received frame if the adapter stops sending for this long.
+const FRAME_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How many ApsDataConfirms to buffer for each slow `Deconz::confirms` stream.
+const CONFIRMS_CAPACITY: usize = 16;
+
+/// How many MacPolls to buffer for each slow `Deconz::mac_polls` stream.
+const MAC_POLLS_CAPACITY: usize = 16;
+
+/// How many configuration changes to buffer for each slow `Deconz::config_changed` stream.
+const CONFIG_CHANGED_CAPACITY: usize = 1;
+
+/// How many network state changes to buffer for each slow `Deconz::network_state_changes` stream.
+const NETWORK_STATE_CHANGES_CAPACITY: usize = 4;
+
 #[derive(Clone)]
 pub struct Deconz {
     commands: mpsc::Sender<SerialCommand>,
     aps_data_requests: mpsc::Sender<ApsRequest>,
+    aps_awaiting: aps::Awaiting,
+    confirm_timeout: Duration,
+    device_state: watch::Receiver<DeviceState>,
+    confirms: broadcast::Sender<(RequestId, ApsDataConfirm)>,
+    mac_polls: broadcast::Sender<ShortAddress>,
+    config_changed: broadcast::Sender<()>,
+    network_state_changes: broadcast::Sender<NetworkStateChange>,
+    max_aps_payload: usize,
+    endpoints: EndpointRegistry,
     sequence_ids: IncrementingId,
     request_ids: IncrementingId,
+    shutdown: Arc<watch::Sender<bool>>,
+    metrics: Arc<dyn Metrics>,
 }
 
 impl Deconz {
+    /// Talks to the adapter over any transport, such as a serial port (see `open_tty`) or a TCP
+    /// connection to a serial bridge. Must be called from within a Tokio runtime, as it spawns
+    /// the tasks that drive the connection.
+    ///
+    /// Returns a handle for making requests, and a reader for ApsDataIndications that aren't
+    /// destined for a registered endpoint.
     pub fn new<R, W>(reader: R, writer: W) -> (Self, ApsReader)
     where
         R: AsyncRead + Send + Unpin + 'static,
         W: AsyncWrite + Send + Unpin + 'static,
     {
-        let reader = slip::Reader::new(reader);
-        let writer = slip::Writer::new(writer);
+        Self::with_config(reader, writer, DeconzConfig::default())
+    }
+
+    /// Like `new`, but with non-default queue depths, checksum or metrics.
+    pub fn with_config<R, W>(reader: R, writer: W, config: DeconzConfig) -> (Self, ApsReader)
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let checksum = config.checksum;
+        let (deconz, aps_reader, link) = Self::spawn_aps_tasks(config);
+        let shutdown = link.shutdown.clone();
+        let (rx, tx) = link.attach(reader, writer, checksum);
+
+        tokio::spawn(until_shutdown(shutdown.clone(), rx.task()));
+        tokio::spawn(until_shutdown(shutdown, tx.task()));
 
-        let (commands_tx, commands_rx) = mpsc::channel(1);
+        (deconz, aps_reader)
+    }
+
+    /// Like `with_config`, but calls `open` to re-open the transport whenever reading from or
+    /// writing to it fails (e.g. because the adapter was briefly unplugged). The returned handles
+    /// remain usable across reconnections.
+    ///
+    /// Requests in-flight when the transport fails, or made while reconnecting, fail with
+    /// `ErrorKind::Disconnected`.
+    pub fn new_reconnecting<F, R, W>(
+        mut open: F,
+        config: DeconzConfig,
+        retry_policy: RetryPolicy,
+    ) -> Result<(Self, ApsReader)>
+    where
+        F: FnMut() -> Result<(R, W)> + Send + 'static,
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let (reader, writer) = open()?;
+
+        let checksum = config.checksum;
+        let (deconz, aps_reader, link) = Self::spawn_aps_tasks(config);
+        let shutdown = link.shutdown.clone();
+        let (rx, tx) = link.attach(reader, writer, checksum);
+
+        let task = reconnect(rx, tx, open, checksum, retry_policy);
+        tokio::spawn(until_shutdown(shutdown, task));
+
+        Ok((deconz, aps_reader))
+    }
+
+    /// Creates the public handles and spawns the Aps tasks, which don't depend on the transport.
+    /// Returns the `Link` that a transport should be attached to.
+    fn spawn_aps_tasks(config: DeconzConfig) -> (Self, ApsReader, Link) {
+        let metrics = config.metrics;
+        // A channel can't be created without any capacity, so treat zero as the smallest queue.
+        let command_queue = config.command_queue.max(1);
+        let indication_queue = config.indication_queue.max(1);
+        let request_queue = config.request_queue.max(1);
+
+        let (commands_tx, commands_rx) = mpsc::channel(command_queue);
         let (device_state_tx, device_state_rx) = watch::channel(DeviceState::default());
-        let (aps_data_indications_tx, aps_data_indications_rx) = mpsc::channel(1);
-        let (aps_data_requests_tx, aps_data_requests_rx) = mpsc::channel(1);
+        let (aps_data_indications_tx, aps_data_indications_rx) = mpsc::channel(indication_queue);
+        let (aps_data_requests_tx, aps_data_requests_rx) = mpsc::channel(request_queue);
+        let (confirms_tx, _) = broadcast::channel(CONFIRMS_CAPACITY);
+        let (mac_polls_tx, _) = broadcast::channel(MAC_POLLS_CAPACITY);
+        let (config_changed_tx, _) = broadcast::channel(CONFIG_CHANGED_CAPACITY);
+        let (network_state_changes_tx, _) = broadcast::channel(NETWORK_STATE_CHANGES_CAPACITY);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let awaiting = aps::Awaiting::new();
 
         let deconz = Self {
             commands: commands_tx,
             aps_data_requests: aps_data_requests_tx,
+            aps_awaiting: awaiting.clone(),
+            confirm_timeout: config.confirm_timeout,
+            device_state: device_state_rx.clone(),
+            confirms: confirms_tx.clone(),
+            mac_polls: mac_polls_tx.clone(),
+            config_changed: config_changed_tx.clone(),
+            network_state_changes: network_state_changes_tx.clone(),
+            max_aps_payload: config.max_aps_payload,
+            endpoints: EndpointRegistry::new(indication_queue, metrics.clone()),
             sequence_ids: IncrementingId::new(),
             request_ids: IncrementingId::new(),
+            shutdown: Arc::new(shutdown_tx),
+            metrics: metrics.clone(),
         };
-        let aps_reader = ApsReader {
-            rx: aps_data_indications_rx,
-        };
-
-        let awaiting = Awaiting::new();
-        let rx = Rx {
-            awaiting: awaiting.clone(),
-            reader,
-            device_state: device_state_tx,
-        };
-        let tx = Tx {
-            awaiting,
-            writer,
+        let aps_reader = ApsReader::new(aps_data_indications_rx, indication_queue, metrics.clone());
+        let link = Link {
+            awaiting: Awaiting::new(),
+            spans: Spans::default(),
             commands: commands_rx,
+            device_state: device_state_tx,
+            mac_polls: mac_polls_tx,
+            config_changed: config_changed_tx,
+            network_state_changes: network_state_changes_tx,
+            shutdown: shutdown_rx.clone(),
+            metrics,
         };
 
-        let awaiting = aps::Awaiting::new();
         let aps_requests = ApsRequests {
             deconz: deconz.clone(),
             device_state: device_state_rx.clone(),
@@ -76,20 +207,32 @@ impl Deconz {
             deconz: deconz.clone(),
             device_state: device_state_rx.clone(),
             awaiting: awaiting.clone(),
+            confirms: confirms_tx,
         };
         let aps_indications = ApsIndications {
             deconz: deconz.clone(),
             device_state: device_state_rx,
             aps_data_indications: aps_data_indications_tx,
+            endpoints: deconz.endpoints.clone(),
+            metrics: deconz.metrics.clone(),
         };
 
-        tokio::spawn(rx.task());
-        tokio::spawn(tx.task());
-        tokio::spawn(aps_requests.task());
-        tokio::spawn(aps_confirms.task());
-        tokio::spawn(aps_indications.task());
+        tokio::spawn(until_shutdown(shutdown_rx.clone(), aps_requests.task()));
+        tokio::spawn(until_shutdown(shutdown_rx.clone(), aps_confirms.task()));
+        tokio::spawn(until_shutdown(shutdown_rx.clone(), aps_indications.task()));
 
-        (deconz, aps_reader)
+        if let Some(period) = config.keepalive {
+            let task = keepalive(deconz.clone(), period);
+            tokio::spawn(until_shutdown(shutdown_rx, task));
+        }
+
+        (deconz, aps_reader, link)
+    }
+
+    /// Stops the tasks driving the connection, which closes the transport. This affects every
+    /// clone of this handle: any outstanding or later requests fail, and the `ApsReader`s end.
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.broadcast(true);
     }
 
     fn sequence_id(&self) -> SequenceId {
@@ -100,18 +243,47 @@ impl Deconz {
         self.request_ids.next()
     }
 
+    fn pending_confirm(
+        &self,
+        request_id: RequestId,
+        receiver: oneshot::Receiver<Result<ApsDataConfirm>>,
+    ) -> PendingConfirm {
+        PendingConfirm {
+            request_id,
+            receiver,
+            timeout: tokio::time::delay_for(self.confirm_timeout),
+            awaiting: self.aps_awaiting.clone(),
+        }
+    }
+
     pub async fn make_request(&self, request: Request) -> Result<Response> {
-        let (sender, receiver) = oneshot::channel();
         let sequence_id = self.sequence_id();
+        let future = self.send_command(sequence_id, request);
+        #[cfg(feature = "tracing")]
+        let future = future.instrument(debug_span!("request", sequence_id));
+        future.await
+    }
+
+    async fn send_command(&self, sequence_id: SequenceId, request: Request) -> Result<Response> {
+        let (sender, receiver) = oneshot::channel();
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
+        #[cfg(not(feature = "tracing"))]
+        let span = ();
 
         self.commands
             .clone()
-            .send((sequence_id, request, sender))
+            .send((sequence_id, request, sender, span))
             .await
             .map_err(|_| ErrorKind::ChannelError)?;
 
-        let future = tokio::time::timeout(TIMEOUT, receiver);
-        let result = future.await?.map_err(|_| ErrorKind::ChannelError)?;
+        let result = match tokio::time::timeout(TIMEOUT, receiver).await {
+            Ok(result) => result.map_err(|_| ErrorKind::ChannelError)?,
+            Err(elapsed) => {
+                self.metrics.request_timeout();
+                return Err(elapsed.into());
+            }
+        };
         let response = result?;
 
         Ok(response)
@@ -124,6 +296,60 @@ impl Deconz {
         }
     }
 
+    /// The longest ASDU that the adapter's firmware can send, so that callers can tell whether
+    /// longer ASDUs can be sent with `TxOptions::FRAGMENTATION`. See
+    /// `DeconzConfig::max_aps_payload`.
+    pub fn max_aps_payload(&self) -> usize {
+        self.max_aps_payload
+    }
+
+    /// Observe the device state whenever the adapter reports it, e.g. to notice the network going
+    /// offline. Use `wait_until_connected` to wait for it to come up.
+    pub fn subscribe_device_state(&self) -> watch::Receiver<DeviceState> {
+        self.device_state.clone()
+    }
+
+    /// Returns a reader for ApsDataIndications addressed to `endpoint`, which will no longer be
+    /// delivered to the `ApsReader` returned on construction. The endpoint is released when the
+    /// reader is dropped.
+    pub fn register_endpoint(&self, endpoint: Endpoint) -> Result<ApsReader> {
+        self.endpoints.register(endpoint)
+    }
+
+    /// Like `register_endpoint`, but picks the first endpoint that is not already in use.
+    pub fn allocate_endpoint(&self) -> Result<(Endpoint, ApsReader)> {
+        self.endpoints.allocate()
+    }
+
+    /// Observe every ApsDataConfirm received from the adapter, including those that don't match
+    /// any request. Confirms are dropped if the stream isn't read quickly enough.
+    pub fn confirms(&self) -> impl Stream<Item = (RequestId, ApsDataConfirm)> {
+        self.confirms.subscribe().filter_map(|result| result.ok())
+    }
+
+    /// Observe the short address of every device that polls the adapter for data. Sleepy end
+    /// devices poll periodically, so this can be used to tell that they are still alive.
+    pub fn mac_polls(&self) -> impl Stream<Item = ShortAddress> {
+        self.mac_polls.subscribe().filter_map(|result| result.ok())
+    }
+
+    /// Notified whenever the adapter reports that its configuration has changed, e.g. so that
+    /// cached parameters can be read again.
+    pub fn config_changed(&self) -> impl Stream<Item = ()> {
+        self.config_changed
+            .subscribe()
+            .filter_map(|result| result.ok())
+    }
+
+    /// Notified whenever the adapter reports a different `NetworkState`, e.g. to observe a leave
+    /// completing. APS requests fail with `ErrorKind::NotConnected` while the network isn't
+    /// connected.
+    pub fn network_state_changes(&self) -> impl Stream<Item = NetworkStateChange> {
+        self.network_state_changes
+            .subscribe()
+            .filter_map(|result| result.ok())
+    }
+
     pub async fn device_state(&self) -> Result<DeviceState> {
         match self.make_request(Request::DeviceState).await? {
             Response::DeviceState(device_state) => Ok(device_state),
@@ -131,7 +357,108 @@ impl Deconz {
         }
     }
 
+    /// Waits for the network state to become `NetworkState::Connected`, failing with
+    /// `ErrorKind::Timeout` if it doesn't within `timeout`.
+    pub async fn wait_until_connected(&self, timeout: Duration) -> Result<()> {
+        let mut device_state = self.subscribe_device_state();
+
+        let connected = async {
+            // The adapter only reports its state alongside other responses, so ask for it in case
+            // it is already connected. The query may fail or go unanswered, so keep watching for
+            // DeviceStateChanged regardless.
+            let query = self.device_state();
+            tokio::pin!(query);
+            let mut querying = true;
+
+            loop {
+                tokio::select! {
+                    result = &mut query, if querying => {
+                        querying = false;
+                        match result {
+                            Ok(state) if state.network_state.is_operational() => return Ok(()),
+                            Ok(_) => {}
+                            Err(error) => warn!("wait_until_connected: device state: {}", error),
+                        }
+                    }
+                    state = device_state.recv() => match state {
+                        Some(state) if state.network_state.is_operational() => return Ok(()),
+                        Some(_) => {}
+                        None => return Err(ErrorKind::ChannelError.into()),
+                    },
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, connected).await?
+    }
+
+    pub async fn change_network_state(&self, network_state: NetworkState) -> Result<DeviceState> {
+        match self
+            .make_request(Request::ChangeNetworkState(network_state))
+            .await?
+        {
+            Response::ChangeNetworkState(device_state) => Ok(device_state),
+            resp => Err(ErrorKind::UnexpectedResponse(resp.command_id()).into()),
+        }
+    }
+
+    pub async fn read_parameter(&self, parameter_id: ParameterId) -> Result<Parameter> {
+        match self
+            .make_request(Request::ReadParameter { parameter_id })
+            .await?
+        {
+            Response::Parameter(parameter) => Ok(parameter),
+            resp => Err(ErrorKind::UnexpectedResponse(resp.command_id()).into()),
+        }
+    }
+
+    /// Reads several parameters, returning them in the same order as `parameter_ids`. The
+    /// requests are sent concurrently, so are pipelined up to the depth of the command queue.
+    pub async fn read_parameters(&self, parameter_ids: &[ParameterId]) -> Result<Vec<Parameter>> {
+        let handles = parameter_ids
+            .iter()
+            .map(|&parameter_id| {
+                let deconz = self.clone();
+                tokio::spawn(async move { deconz.read_parameter(parameter_id).await })
+            })
+            .collect::<Vec<_>>();
+
+        let mut parameters = Vec::with_capacity(handles.len());
+        for (handle, &parameter_id) in handles.into_iter().zip(parameter_ids) {
+            let result = handle.await.map_err(|_| ErrorKind::ChannelError)?;
+            let parameter = result.map_err(|error| match error.kind {
+                ErrorKind::InvalidParameter { .. } => error,
+                _ => Error {
+                    kind: ErrorKind::InvalidParameter {
+                        parameter_id,
+                        inner: Box::new(error),
+                    },
+                },
+            })?;
+            parameters.push(parameter);
+        }
+
+        Ok(parameters)
+    }
+
+    pub async fn write_parameter(&self, parameter: Parameter) -> Result<()> {
+        let parameter_id = parameter.id();
+
+        match self
+            .make_request(Request::WriteParameter { parameter })
+            .await?
+        {
+            Response::WriteParameter(id) if id == parameter_id => Ok(()),
+            Response::WriteParameter(id) => Err(ErrorKind::UnexpectedParameter(id).into()),
+            resp => Err(ErrorKind::UnexpectedResponse(resp.command_id()).into()),
+        }
+    }
+
+    /// Sends `request` once the adapter has a free slot, and waits for it to be confirmed. Fails
+    /// with `ErrorKind::AsduTooLarge` if the ASDU is longer than the firmware can send.
     pub async fn aps_data_request(&self, request: ApsDataRequest) -> Result<ApsDataConfirm> {
+        request.check_asdu_len(Some(self.max_aps_payload))?;
+
         let (sender, receiver) = oneshot::channel();
         let request_id = self.request_id();
 
@@ -142,10 +469,332 @@ impl Deconz {
             .await
             .map_err(|_| ErrorKind::ChannelError)?;
 
-        let result = receiver.await.map_err(|_| ErrorKind::ChannelError)?;
+        self.pending_confirm(request_id, receiver).await
+    }
+
+    /// Like `aps_data_request`, but fails with `ErrorKind::QueueFull` rather than waiting if the
+    /// request queue is full, so it can be called where blocking isn't possible. Otherwise,
+    /// returns a future that resolves once the request is confirmed.
+    pub fn try_aps_data_request(&self, request: ApsDataRequest) -> Result<PendingConfirm> {
+        request.check_asdu_len(Some(self.max_aps_payload))?;
+
+        let (sender, receiver) = oneshot::channel();
+        let request_id = self.request_id();
+
+        self.aps_data_requests
+            .clone()
+            .try_send((request_id, request, sender))
+            .map_err(|error| match error {
+                TrySendError::Full(_) => ErrorKind::QueueFull,
+                TrySendError::Closed(_) => ErrorKind::ChannelError,
+            })?;
+
+        Ok(self.pending_confirm(request_id, receiver))
+    }
+
+    /// A `Sink` for sending ApsDataRequests, e.g. by forwarding a stream into it.
+    pub fn aps_data_sink(&self) -> ApsDataSink {
+        ApsDataSink {
+            deconz: self.clone(),
+            requests: self.aps_data_requests.clone(),
+            pending: FuturesUnordered::new(),
+        }
+    }
+
+    /// Like `aps_data_request`, but resends the request (with a new request ID) if delivery fails
+    /// for a reason that might be transient, such as the destination not acknowledging it.
+    pub async fn aps_data_request_retry(
+        &self,
+        request: ApsDataRequest,
+        retry_policy: RetryPolicy,
+    ) -> Result<ApsDataConfirm> {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match self.aps_data_request(request.clone()).await {
+                Err(Error {
+                    kind: ErrorKind::ApsDeliveryFailed(status),
+                }) if status.is_retryable() && attempts < retry_policy.max_attempts => {
+                    debug!("aps_data_request: {:?}, retrying", status);
+                    tokio::time::delay_for(retry_policy.backoff).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// The confirmation of an ApsDataRequest queued by `Deconz::try_aps_data_request`. Resolves to an
+/// error if the request couldn't be delivered, or with `ErrorKind::ConfirmTimeout` if it isn't
+/// confirmed within `DeconzConfig::confirm_timeout` of being queued.
+pub struct PendingConfirm {
+    request_id: RequestId,
+    receiver: oneshot::Receiver<Result<ApsDataConfirm>>,
+    timeout: tokio::time::Delay,
+    awaiting: aps::Awaiting,
+}
+
+impl Future for PendingConfirm {
+    type Output = Result<ApsDataConfirm>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(result) => result.map_err(|_| ErrorKind::ChannelError)?,
+            Poll::Pending => {
+                if Pin::new(&mut self.timeout).poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                // The confirm may never arrive, so don't keep waiting for it.
+                self.awaiting.deregister(&self.request_id);
+                return Poll::Ready(Err(ErrorKind::ConfirmTimeout(self.request_id).into()));
+            }
+        };
         let aps_data_confirm = result?;
 
-        Ok(aps_data_confirm)
+        Poll::Ready(match aps_data_confirm.status {
+            ConfirmStatus::Success => Ok(aps_data_confirm),
+            status => Err(ErrorKind::ApsDeliveryFailed(status).into()),
+        })
+    }
+}
+
+/// Sends ApsDataRequests, as created by `Deconz::aps_data_sink`. The sink is ready whenever the
+/// request queue has space. Flushing waits for every request sent so far to be confirmed, and fails
+/// if any couldn't be delivered.
+pub struct ApsDataSink {
+    deconz: Deconz,
+    requests: mpsc::Sender<ApsRequest>,
+    pending: FuturesUnordered<PendingConfirm>,
+}
+
+impl ApsDataSink {
+    /// Polls the confirms of the requests sent so far, returning the first failure.
+    fn poll_confirms(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        loop {
+            match Pin::new(&mut self.pending).poll_next(cx) {
+                Poll::Ready(Some(Ok(_))) => continue,
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl Sink<ApsDataRequest> for ApsDataSink {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        // Report failures as soon as we know of them, without waiting for outstanding confirms.
+        if let Poll::Ready(Err(error)) = self.poll_confirms(cx) {
+            return Poll::Ready(Err(error));
+        }
+
+        self.requests
+            .poll_ready(cx)
+            .map_err(|_| ErrorKind::ChannelError.into())
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, request: ApsDataRequest) -> Result<()> {
+        request.check_asdu_len(Some(self.deconz.max_aps_payload))?;
+
+        let (sender, receiver) = oneshot::channel();
+        let request_id = self.deconz.request_id();
+
+        self.requests
+            .try_send((request_id, request, sender))
+            .map_err(|error| match error {
+                TrySendError::Full(_) => ErrorKind::QueueFull,
+                TrySendError::Closed(_) => ErrorKind::ChannelError,
+            })?;
+        let pending_confirm = self.deconz.pending_confirm(request_id, receiver);
+        self.pending.push(pending_confirm);
+
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_confirms(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_confirms(cx)
+    }
+}
+
+/// The depths of the queues between the `Deconz` handles and the tasks that service them, how long
+/// to wait on the adapter, and how frames on the link are checked and reported. Queues of zero are
+/// given a depth of one.
+#[derive(Clone)]
+pub struct DeconzConfig {
+    /// Commands waiting to be written to the adapter.
+    pub command_queue: usize,
+    /// ApsDataIndications waiting to be read from each `ApsReader`. Further indications are
+    /// dropped until the reader catches up.
+    pub indication_queue: usize,
+    /// ApsDataRequests waiting for the adapter to have a free slot.
+    pub request_queue: usize,
+    /// If set, read the device state this often. Some firmware stops sending DeviceStateChanged
+    /// when the host is idle, which stalls the delivery of ApsDataIndications and confirms.
+    pub keepalive: Option<Duration>,
+    /// How long to wait for an ApsDataRequest to be confirmed, including the time spent queueing.
+    /// Delivery to a sleepy end device may take several seconds, until it next polls for data.
+    pub confirm_timeout: Duration,
+    /// The longest ASDU that the adapter's firmware can send with `TxOptions::FRAGMENTATION`,
+    /// which requests are checked against before they're queued. The firmware doesn't report its
+    /// limit, so this defaults to `ApsDataRequest::MAX_ASDU_LEN` (i.e. no fragmentation): raise it
+    /// if the adapter is known to accept longer ASDUs.
+    pub max_aps_payload: usize,
+    /// The SLIP checksum. Only `Checksum::Sum` is understood by the deCONZ firmware.
+    pub checksum: Checksum,
+    /// Notified of events on the link, e.g. frames discarded because of checksum errors.
+    pub metrics: Arc<dyn Metrics>,
+}
+
+impl Default for DeconzConfig {
+    fn default() -> Self {
+        Self {
+            command_queue: 1,
+            indication_queue: 16,
+            request_queue: 1,
+            keepalive: None,
+            confirm_timeout: Duration::from_secs(10),
+            max_aps_payload: ApsDataRequest::MAX_ASDU_LEN,
+            checksum: Checksum::default(),
+            metrics: Arc::new(NoMetrics),
+        }
+    }
+}
+
+impl fmt::Debug for DeconzConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeconzConfig")
+            .field("command_queue", &self.command_queue)
+            .field("indication_queue", &self.indication_queue)
+            .field("request_queue", &self.request_queue)
+            .field("keepalive", &self.keepalive)
+            .field("confirm_timeout", &self.confirm_timeout)
+            .field("max_aps_payload", &self.max_aps_payload)
+            .field("checksum", &self.checksum)
+            .finish_non_exhaustive()
+    }
+}
+
+/// How many times to retry an operation, and how long to wait between attempts.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: usize,
+    pub backoff: Duration,
+}
+
+/// The parts of the serial link that outlive any particular transport.
+struct Link {
+    awaiting: Awaiting,
+    spans: Spans,
+    commands: mpsc::Receiver<SerialCommand>,
+    device_state: watch::Sender<DeviceState>,
+    mac_polls: broadcast::Sender<ShortAddress>,
+    config_changed: broadcast::Sender<()>,
+    network_state_changes: broadcast::Sender<NetworkStateChange>,
+    shutdown: watch::Receiver<bool>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl Link {
+    fn attach<R, W>(self, reader: R, writer: W, checksum: Checksum) -> (Rx<R>, Tx<W>)
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let rx = Rx {
+            awaiting: self.awaiting.clone(),
+            spans: self.spans.clone(),
+            reader: slip::Reader::new_with_timeout(reader, checksum, FRAME_TIMEOUT),
+            device_state: self.device_state,
+            mac_polls: self.mac_polls,
+            config_changed: self.config_changed,
+            configuration_changed: false,
+            network_state_changes: self.network_state_changes,
+            network_state: None,
+            metrics: self.metrics.clone(),
+        };
+        let tx = Tx {
+            awaiting: self.awaiting,
+            spans: self.spans,
+            writer: slip::Writer::new(writer, checksum),
+            commands: self.commands,
+            metrics: self.metrics,
+        };
+        (rx, tx)
+    }
+}
+
+/// Drives `task` until it completes, or until `Deconz::shutdown` is called.
+async fn until_shutdown<F>(mut shutdown: watch::Receiver<bool>, task: F) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    let stopped = async move { while let Some(false) = shutdown.recv().await {} };
+    tokio::select! {
+        result = task => result,
+        _ = stopped => Ok(()),
+    }
+}
+
+/// Task that reads the device state every `period`, so that the Aps tasks see any changes.
+async fn keepalive(deconz: Deconz, period: Duration) -> Result<()> {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        if let Err(error) = deconz.device_state().await {
+            error!("keepalive: {}", error);
+        }
+    }
+}
+
+/// Task that runs the Rx and Tx tasks, re-opening the transport whenever either of them fails.
+async fn reconnect<F, R, W>(
+    mut rx: Rx<R>,
+    mut tx: Tx<W>,
+    mut open: F,
+    checksum: Checksum,
+    retry_policy: RetryPolicy,
+) -> Result<()>
+where
+    F: FnMut() -> Result<(R, W)>,
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        tokio::select! {
+            _ = rx.run() => {}
+            result = tx.run() => {
+                // All of the Deconz handles have been dropped.
+                if result.is_ok() {
+                    return Ok(());
+                }
+            }
+        }
+
+        // Anything awaiting a response from the old transport will never get one.
+        rx.shutdown();
+
+        let mut attempts = 0;
+        let (reader, writer) = loop {
+            attempts += 1;
+            match open() {
+                Ok(transport) => break transport,
+                Err(error) if attempts < retry_policy.max_attempts => {
+                    error!("reconnect: {}", error);
+                    tx.reject_commands(retry_policy.backoff).await;
+                }
+                Err(error) => return Err(error),
+            }
+        };
+        info!("reconnected after {} attempt(s)", attempts);
+
+        rx.reader = slip::Reader::new_with_timeout(reader, checksum, FRAME_TIMEOUT);
+        tx.writer = slip::Writer::new(writer, checksum);
     }
 }
 
@@ -158,8 +807,18 @@ where
     R: AsyncRead + Unpin,
 {
     awaiting: Awaiting,
+    spans: Spans,
     reader: slip::Reader<R>,
     device_state: watch::Sender<DeviceState>,
+    mac_polls: broadcast::Sender<ShortAddress>,
+    config_changed: broadcast::Sender<()>,
+    /// The configuration_changed bit of the last DeviceState, so that we only notify once each
+    /// time it is set.
+    configuration_changed: bool,
+    network_state_changes: broadcast::Sender<NetworkStateChange>,
+    /// The network_state of the last DeviceState, so that we only notify when it changes.
+    network_state: Option<NetworkState>,
+    metrics: Arc<dyn Metrics>,
 }
 
 impl<R> Rx<R>
@@ -167,37 +826,91 @@ where
     R: AsyncRead + Unpin,
 {
     async fn task(mut self) -> Result<()> {
+        let result = self.run().await;
+        self.shutdown();
+        result
+    }
+
+    /// Fail any requests that are awaiting a response, as none will arrive once we've stopped
+    /// reading.
+    fn shutdown(&self) {
+        self.awaiting.cancel_all(|| ErrorKind::Disconnected.into());
+        self.spans.lock().expect("poisoned").clear();
+    }
+
+    async fn run(&mut self) -> Result<()> {
+        // Reused for every frame, to avoid allocating.
+        let mut frame = Vec::new();
         loop {
-            let frame = match self.read_frame().await {
-                Ok(frame) => frame,
+            match self.read_frame(&mut frame).await {
+                Ok(()) => {}
+                Err(error) if error.is_disconnect() => {
+                    error!("rx read_frame: serial port disconnected: {}", error);
+                    return Err(error);
+                }
                 Err(error) => {
                     error!("rx read_frame: {}", error);
                     continue;
                 }
             };
 
-            if let Err(error) = self.process_frame(frame).await {
+            if let Err(error) = self.process_frame(&frame).await {
                 error!("rx process_frame: {}", error);
             }
         }
     }
 
-    async fn read_frame(&mut self) -> Result<Vec<u8>> {
-        let frame = self.reader.read_frame().await?;
-        debug!("received frame = {:?}", frame);
+    async fn read_frame(&mut self, frame: &mut Vec<u8>) -> Result<()> {
+        if let Err(error) = self.reader.read_frame_into(frame).await {
+            match error.kind {
+                ErrorKind::Slip(SlipError::MismatchedCrc) => self.metrics.crc_mismatch(),
+                ErrorKind::Slip(SlipError::InvalidEscape) => self.metrics.invalid_escape(),
+                _ => {}
+            }
+            return Err(error);
+        }
+        self.metrics.frame_received();
+        debug!("received frame = {}", loggable_frame(frame));
 
-        Ok(frame)
+        Ok(())
     }
 
-    async fn process_frame(&mut self, frame: Vec<u8>) -> Result<()> {
+    async fn process_frame(&mut self, frame: &[u8]) -> Result<()> {
         let sequence_id = frame[1];
+        // Log within the span of the request this is a response to, if any.
+        #[cfg(feature = "tracing")]
+        let _span = match self.spans.lock().expect("poisoned").get(&sequence_id) {
+            Some(span) => debug_span!(parent: span, "rx", sequence_id),
+            None => debug_span!("rx", sequence_id),
+        }
+        .entered();
 
         let result = Response::from_frame(frame);
         if let Ok(response) = &result {
-            debug!("received response = {:?}", response);
+            debug!("received response (seq {}) = {:?}", sequence_id, response);
 
             if let Some(device_state) = response.device_state() {
                 let _ = self.device_state.broadcast(device_state);
+
+                if device_state.configuration_changed && !self.configuration_changed {
+                    let _ = self.config_changed.send(());
+                }
+                self.configuration_changed = device_state.configuration_changed;
+
+                let to = device_state.network_state;
+                if let Some(from) = self.network_state.replace(to) {
+                    if from != to {
+                        debug!("network state changed from {:?} to {:?}", from, to);
+                        let _ = self
+                            .network_state_changes
+                            .send(NetworkStateChange { from, to });
+                    }
+                }
+            }
+
+            if let Response::MacPoll { address } = response {
+                // There may be no one observing polls, which is fine.
+                let _ = self.mac_polls.send(*address);
             }
 
             // It might just have been a notification from Deconz, in which case we only want to
@@ -211,6 +924,7 @@ where
             .awaiting
             .deregister(&sequence_id)
             .ok_or(ErrorKind::UnsolicitedResponse(sequence_id))?;
+        self.spans.lock().expect("poisoned").remove(&sequence_id);
         let _ = sender.send(result);
 
         Ok(())
@@ -223,8 +937,10 @@ where
     W: AsyncWrite + Unpin,
 {
     awaiting: Awaiting,
+    spans: Spans,
     writer: slip::Writer<W>,
     commands: mpsc::Receiver<SerialCommand>,
+    metrics: Arc<dyn Metrics>,
 }
 
 impl<W> Tx<W>
@@ -232,20 +948,405 @@ where
     W: AsyncWrite + Unpin,
 {
     async fn task(mut self) -> Result<()> {
-        while let Some((sequence_id, request, sender)) = self.commands.recv().await {
-            let awaiting = self.awaiting.clone();
+        let result = self.run().await;
+        if result.is_err() {
+            // There's no transport to send anything on, so fail whatever is waiting for one.
+            self.awaiting.cancel_all(|| ErrorKind::Disconnected.into());
+            self.spans.lock().expect("poisoned").clear();
+            while let Some((_, _, sender, _)) = self.commands.recv().await {
+                let _ = sender.send(Err(ErrorKind::Disconnected.into()));
+            }
+        }
+        result
+    }
+
+    /// Sends commands until every `Deconz` handle has been dropped, or until writing to the
+    /// transport fails, in which case the request being written is failed with the error.
+    async fn run(&mut self) -> Result<()> {
+        while let Some((sequence_id, request, sender, span)) = self.commands.recv().await {
+            // The sequence ID has wrapped around before an earlier request got a response. We
+            // can't tell which request a response would be for, so fail the earlier one rather
+            // than leave it to time out.
+            if let Some(previous) = self.awaiting.deregister(&sequence_id) {
+                let _ = previous.send(Err(ErrorKind::DuplicateSequenceId(sequence_id).into()));
+            }
+
+            #[cfg(feature = "tracing")]
+            let tx_span = debug_span!(parent: &span, "tx", sequence_id);
+            self.spans
+                .lock()
+                .expect("poisoned")
+                .insert(sequence_id, span);
+
+            // Register before sending, in case the response arrives before the write returns.
+            self.awaiting.register(sequence_id, sender);
             let future = self.send_request(sequence_id, request);
-            awaiting.register_while(sequence_id, sender, future).await;
+            #[cfg(feature = "tracing")]
+            let future = future.instrument(tx_span);
+            if let Err(error) = future.await {
+                let disconnected = error.is_disconnect();
+                if disconnected {
+                    error!("tx write_frame: serial port disconnected: {}", error);
+                }
+                self.awaiting.send(&sequence_id, Err(error));
+                if disconnected {
+                    return Err(ErrorKind::Disconnected.into());
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Fail any commands received in the next `duration`, whilst there is no transport to send
+    /// them on.
+    async fn reject_commands(&mut self, duration: Duration) {
+        let mut delay = tokio::time::delay_for(duration);
+        loop {
+            tokio::select! {
+                _ = &mut delay => return,
+                command = self.commands.recv() => match command {
+                    Some((_, _, sender, _)) => {
+                        let _ = sender.send(Err(ErrorKind::Disconnected.into()));
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+
+    /// Encodes and writes `request`. Any error is propagated back to the caller of `make_request`
+    /// by `run`.
     async fn send_request(&mut self, sequence_id: SequenceId, request: Request) -> Result<()> {
-        debug!("sending request = {:?}", request);
+        debug!("sending request (seq {}) = {:?}", sequence_id, request);
         let frame = request.into_frame(sequence_id)?;
-        debug!("sending frame = {:?}", frame);
+        debug!(
+            "sending frame (seq {}) = {}",
+            sequence_id,
+            loggable_frame(&frame)
+        );
         self.writer.write_frame(&frame).await?;
+        self.metrics.frame_sent();
         Ok(())
     }
 }
+
+/// Formats a frame with `hexdump`, unless it carries the network key (which is redacted).
+fn loggable_frame(frame: &[u8]) -> String {
+    // The parameter ID follows the header and payload length.
+    let command_id = frame.first().map(|&byte| CommandId::try_from(byte));
+    let parameter_id = frame.get(7..).map(|mut r| ParameterId::read_wire(&mut r));
+    match (command_id, parameter_id) {
+        (
+            Some(Ok(CommandId::ReadParameter)) | Some(Ok(CommandId::WriteParameter)),
+            Some(Ok(ParameterId::NetworkKey)),
+        ) => "<redacted>".to_owned(),
+        _ => hexdump(frame),
+    }
+}
+
+/// Formats bytes as space-separated hex, for comparing frames against packet captures.
+fn hexdump(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, MockAdapter};
+    use crate::{ClusterId, Destination, NetworkKey, TxOptions};
+
+    const VERSION: Version = Version {
+        major: 0x26,
+        minor: 0x50,
+    };
+
+    #[tokio::test]
+    async fn zero_queues_are_given_a_depth_of_one() {
+        let config = DeconzConfig {
+            command_queue: 0,
+            indication_queue: 0,
+            request_queue: 0,
+            ..DeconzConfig::default()
+        };
+        let (deconz, _aps_reader, adapter) = MockAdapter::connect_with_config(config);
+        adapter.respond(Response::Version {
+            version: VERSION,
+            platform: Platform::Arm,
+        });
+
+        let (version, platform) = deconz.version().await.unwrap();
+        assert_eq!(version, VERSION);
+        assert_eq!(platform, Platform::Arm);
+        deconz.register_endpoint(Endpoint(1)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn duplicate_sequence_id_fails_earlier_request() {
+        // The adapter never answers, so both requests are still awaiting a response.
+        let (deconz, _aps_reader, _adapter) = MockAdapter::connect();
+
+        let first = deconz.send_command(5, Request::Version);
+        let second = deconz.send_command(5, Request::DeviceState);
+        let (first, _) = tokio::join!(
+            first,
+            tokio::time::timeout(Duration::from_millis(100), second)
+        );
+        match first {
+            Err(Error {
+                kind: ErrorKind::DuplicateSequenceId(5),
+            }) => {}
+            result => panic!("expected DuplicateSequenceId, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_until_connected_by_device_state_changed() {
+        // The adapter never answers the query for its state, but reports it unsolicited.
+        let (deconz, _aps_reader, adapter) = MockAdapter::connect();
+
+        let connected = deconz.wait_until_connected(Duration::from_secs(1));
+        let changed = async {
+            tokio::time::delay_for(Duration::from_millis(50)).await;
+            adapter
+                .send(Response::DeviceStateChanged(DeviceState {
+                    network_state: NetworkState::Connected,
+                    data_confirm: false,
+                    data_indication: false,
+                    data_request_free_slots: true,
+                    configuration_changed: false,
+                }))
+                .await
+        };
+        let (connected, changed) = tokio::join!(connected, changed);
+        changed.unwrap();
+        connected.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wait_until_connected_times_out() {
+        let (deconz, _aps_reader, adapter) = MockAdapter::connect();
+        adapter.respond(Response::DeviceState(DeviceState::default()));
+
+        match deconz
+            .wait_until_connected(Duration::from_millis(100))
+            .await
+        {
+            Err(Error {
+                kind: ErrorKind::Timeout,
+            }) => {}
+            result => panic!("expected Timeout, got {:?}", result),
+        }
+    }
+
+    fn fragmented_request(len: usize) -> ApsDataRequest {
+        ApsDataRequest::builder()
+            .destination(Destination::Nwk(ShortAddress(0x1234), Endpoint(1)))
+            .cluster(ClusterId::OTA_UPGRADE)
+            .asdu(vec![0; len])
+            .tx_options(TxOptions::FRAGMENTATION)
+            .build()
+            .unwrap()
+    }
+
+    fn assert_too_large<T: fmt::Debug>(
+        result: Result<T>,
+        expected_len: usize,
+        expected_max: usize,
+    ) {
+        match result {
+            Err(Error {
+                kind: ErrorKind::AsduTooLarge { len, max },
+            }) => {
+                assert_eq!(len, expected_len);
+                assert_eq!(max, expected_max);
+            }
+            result => panic!("expected AsduTooLarge, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn fragmented_asdu_checked_against_config() {
+        // Without a configured limit, fragmentation doesn't allow longer ASDUs.
+        let (deconz, _aps_reader, _adapter) = MockAdapter::connect();
+        let max = ApsDataRequest::MAX_ASDU_LEN;
+        let result = deconz.aps_data_request(fragmented_request(max + 1)).await;
+        assert_too_large(result, max + 1, max);
+
+        let config = DeconzConfig {
+            max_aps_payload: 256,
+            ..DeconzConfig::default()
+        };
+        let (deconz, _aps_reader, _adapter) = MockAdapter::connect_with_config(config);
+        assert_eq!(deconz.max_aps_payload(), 256);
+        let result = deconz.aps_data_request(fragmented_request(257)).await;
+        assert_too_large(result, 257, 256);
+        let result = deconz.try_aps_data_request(fragmented_request(257));
+        assert_too_large(result.map(|_| ()), 257, 256);
+        let mut sink = deconz.aps_data_sink();
+        let result = Pin::new(&mut sink).start_send(fragmented_request(257));
+        assert_too_large(result, 257, 256);
+    }
+
+    #[test]
+    fn network_key_frames_are_redacted() {
+        let write = Request::WriteParameter {
+            parameter: Parameter::NetworkKey(NetworkKey([0xA5; 16])),
+        };
+        let frame = write.into_frame(1).unwrap();
+        assert_eq!(loggable_frame(&frame), "<redacted>");
+
+        let read = Response::Parameter(Parameter::NetworkKey(NetworkKey([0xA5; 16])));
+        let frame = read.into_frame(1).unwrap();
+        assert_eq!(loggable_frame(&frame), "<redacted>");
+
+        let frame = Request::DeviceState.into_frame(1).unwrap();
+        assert_eq!(loggable_frame(&frame), "07 01 00 05 00");
+    }
+
+    /// A serial port that fails every write.
+    struct FailingWriter;
+
+    impl AsyncWrite for FailingWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn write_error_fails_request() {
+        // Nothing is ever read, but the port stays open.
+        let (reader, _adapter) = testing::duplex();
+        let (deconz, _aps_reader) = Deconz::new(reader, FailingWriter);
+
+        let result = tokio::time::timeout(Duration::from_secs(1), deconz.version())
+            .await
+            .expect("request should fail rather than time out");
+        match result {
+            Err(Error {
+                kind: ErrorKind::Io(error),
+            }) => assert_eq!(error.kind(), std::io::ErrorKind::BrokenPipe),
+            result => panic!("expected a write error, got {:?}", result),
+        }
+    }
+
+    /// A serial port whose reads fail the way an unplugged USB tty's do: with EIO, not EOF.
+    struct UnpluggedReader;
+
+    impl AsyncRead for UnpluggedReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            // EIO
+            Poll::Ready(Err(std::io::Error::from_raw_os_error(5)))
+        }
+    }
+
+    /// A serial port that never has anything to read.
+    struct SilentReader;
+
+    impl AsyncRead for SilentReader {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Pending
+        }
+    }
+
+    type Transport = (
+        Box<dyn AsyncRead + Send + Unpin>,
+        Box<dyn AsyncWrite + Send + Unpin>,
+    );
+
+    /// Connects a `Deconz` that first opens `broken`, and then a transport to a `MockAdapter`.
+    /// The returned receiver resolves once the transport has been re-opened.
+    fn connect_reconnecting(
+        broken: Transport,
+    ) -> (Deconz, ApsReader, MockAdapter, oneshot::Receiver<()>) {
+        let (left, right) = testing::duplex();
+        let adapter = MockAdapter::new(right);
+        let (reader, writer) = tokio::io::split(left);
+        let mut transports: Vec<Transport> = vec![(Box::new(reader), Box::new(writer)), broken];
+
+        let (reopened_tx, reopened) = oneshot::channel();
+        let mut reopened_tx = Some(reopened_tx);
+        let open = move || {
+            let transport = transports.pop().ok_or(ErrorKind::Disconnected)?;
+            if transports.is_empty() {
+                if let Some(reopened_tx) = reopened_tx.take() {
+                    let _ = reopened_tx.send(());
+                }
+            }
+            Ok(transport)
+        };
+
+        let retry_policy = RetryPolicy {
+            max_attempts: 1,
+            backoff: Duration::from_millis(10),
+        };
+        let (deconz, aps_reader) =
+            Deconz::new_reconnecting(open, DeconzConfig::default(), retry_policy).unwrap();
+        (deconz, aps_reader, adapter, reopened)
+    }
+
+    async fn assert_reconnected(
+        deconz: &Deconz,
+        adapter: &MockAdapter,
+        reopened: oneshot::Receiver<()>,
+    ) {
+        tokio::time::timeout(Duration::from_secs(1), reopened)
+            .await
+            .expect("transport should be re-opened")
+            .unwrap();
+
+        adapter.respond(Response::Version {
+            version: VERSION,
+            platform: Platform::Arm,
+        });
+        let (version, _) = deconz.version().await.unwrap();
+        assert_eq!(version, VERSION);
+    }
+
+    #[tokio::test]
+    async fn read_error_reconnects() {
+        let broken: Transport = (Box::new(UnpluggedReader), Box::new(tokio::io::sink()));
+        let (deconz, _aps_reader, adapter, reopened) = connect_reconnecting(broken);
+
+        assert_reconnected(&deconz, &adapter, reopened).await;
+    }
+
+    #[tokio::test]
+    async fn write_error_reconnects() {
+        let broken: Transport = (Box::new(SilentReader), Box::new(FailingWriter));
+        let (deconz, _aps_reader, adapter, reopened) = connect_reconnecting(broken);
+
+        // The request being written sees the error, and the transport is re-opened.
+        match deconz.version().await {
+            Err(Error {
+                kind: ErrorKind::Io(error),
+            }) => assert_eq!(error.kind(), std::io::ErrorKind::BrokenPipe),
+            result => panic!("expected a write error, got {:?}", result),
+        }
+        assert_reconnected(&deconz, &adapter, reopened).await;
+    }
+}
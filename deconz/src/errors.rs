@@ -1,24 +1,41 @@
 use std::fmt::{self, Display};
 
 use crate::protocol::RequestId;
-use crate::{CommandId, ParameterId, SequenceId, SlipError};
+use crate::{CommandId, ConfirmStatus, Endpoint, ParameterId, SequenceId, SlipError};
 
 #[derive(Debug)]
 pub enum ErrorKind {
     DuplicateSequenceId(SequenceId),
     UnsolicitedResponse(SequenceId),
     UnsolicitedConfirm(RequestId),
+    ConfirmTimeout(RequestId),
     UnexpectedResponse(CommandId),
     UnsupportedCommand(u8),
     UnsupportedParameter(u8),
+    UnsupportedSecurityMode(u8),
+    UnsupportedAddressMode(u8),
+    UnexpectedParameter(ParameterId),
+    ParameterWriteRejected(ParameterId),
+    ApsDeliveryFailed(ConfirmStatus),
     InvalidParameter {
         parameter_id: ParameterId,
         inner: Box<Error>,
     },
+    EndpointInUse(Endpoint),
+    NoFreeEndpoints,
+    InvalidExtendedAddress(String),
+    MissingField(&'static str),
+    AsduTooLarge {
+        len: usize,
+        max: usize,
+    },
     Slip(SlipError),
-    SerialPort(tokio_serial::Error),
+    SerialPort(Box<dyn std::error::Error + Send + Sync>),
     Io(std::io::Error),
     Timeout,
+    QueueFull,
+    NotConnected,
+    Disconnected,
     ChannelError,
     Todo,
 }
@@ -35,6 +52,13 @@ impl Display for ErrorKind {
             ErrorKind::UnsolicitedConfirm(request_id) => {
                 write!(f, "unsolicited confirm with request ID: {}", request_id)
             }
+            ErrorKind::ConfirmTimeout(request_id) => {
+                write!(
+                    f,
+                    "timed out waiting for confirm with request ID: {}",
+                    request_id
+                )
+            }
             ErrorKind::UnexpectedResponse(command_id) => {
                 write!(f, "unexpected command ID as response: {}", command_id)
             }
@@ -44,14 +68,41 @@ impl Display for ErrorKind {
             ErrorKind::UnsupportedParameter(parameter_id) => {
                 write!(f, "unsupported parameter ID: {}", parameter_id)
             }
+            ErrorKind::UnsupportedSecurityMode(mode) => {
+                write!(f, "unsupported security mode: {}", mode)
+            }
+            ErrorKind::UnsupportedAddressMode(mode) => {
+                write!(f, "unsupported address mode: {}", mode)
+            }
+            ErrorKind::UnexpectedParameter(parameter_id) => {
+                write!(f, "unexpected parameter ID in response: {}", parameter_id)
+            }
+            ErrorKind::ParameterWriteRejected(parameter_id) => {
+                write!(f, "write rejected for parameter ID: {}", parameter_id)
+            }
+            ErrorKind::ApsDeliveryFailed(status) => {
+                write!(f, "APS data request failed: {:?}", status)
+            }
             ErrorKind::InvalidParameter {
                 parameter_id,
                 inner,
             } => write!(f, "invalid parameter for ID {}: {}", parameter_id, inner),
+            ErrorKind::EndpointInUse(endpoint) => write!(f, "endpoint in use: {}", endpoint),
+            ErrorKind::NoFreeEndpoints => write!(f, "no free endpoints"),
+            ErrorKind::InvalidExtendedAddress(address) => {
+                write!(f, "invalid extended address: {:?}", address)
+            }
+            ErrorKind::MissingField(field) => write!(f, "missing field: {}", field),
+            ErrorKind::AsduTooLarge { len, max } => {
+                write!(f, "ASDU too large: {} bytes, maximum {}", len, max)
+            }
             ErrorKind::Slip(error) => write!(f, "SLIP error: {}", error),
             ErrorKind::SerialPort(error) => write!(f, "serial port error: {}", error),
             ErrorKind::Io(error) => write!(f, "IO error: {}", error),
             ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::QueueFull => write!(f, "queue full"),
+            ErrorKind::NotConnected => write!(f, "not connected to a network"),
+            ErrorKind::Disconnected => write!(f, "disconnected"),
             ErrorKind::ChannelError => write!(f, "channel error"),
             ErrorKind::Todo => write!(f, "TODO, oh no"),
         }
@@ -63,6 +114,22 @@ pub struct Error {
     pub kind: ErrorKind,
 }
 
+impl Error {
+    /// Whether the underlying transport has reached EOF, e.g. because the device was unplugged.
+    pub fn is_eof(&self) -> bool {
+        match &self.kind {
+            ErrorKind::Io(error) => error.kind() == std::io::ErrorKind::UnexpectedEof,
+            _ => false,
+        }
+    }
+
+    /// Whether the underlying transport has failed, e.g. because the device was unplugged. Serial
+    /// ports often report this as an IO error such as EIO or ENXIO, rather than as EOF.
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self.kind, ErrorKind::Io(_))
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "deconz error: {}", self.kind)
@@ -79,10 +146,11 @@ impl From<std::io::Error> for Error {
     }
 }
 
+#[cfg(feature = "serial")]
 impl From<tokio_serial::Error> for Error {
     fn from(other: tokio_serial::Error) -> Self {
         Error {
-            kind: ErrorKind::SerialPort(other),
+            kind: ErrorKind::SerialPort(Box::new(other)),
         }
     }
 }
@@ -1,49 +1,106 @@
 mod aps;
 mod deconz;
 mod errors;
+mod metrics;
 mod parameters;
 mod protocol;
 mod slip;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod types;
 
 #[macro_use]
 extern crate log;
 
 use std::error::Error as StdError;
+#[cfg(feature = "serial")]
 use std::path::Path;
 use std::result::Result as StdResult;
 
+#[cfg(feature = "serial")]
+use tokio::io::{ReadHalf, WriteHalf};
+#[cfg(feature = "serial")]
 use tokio_serial::{Serial, SerialPortSettings};
 
 pub use crate::aps::ApsReader;
-pub use crate::deconz::Deconz;
+pub use crate::deconz::{ApsDataSink, Deconz, DeconzConfig, PendingConfirm, RetryPolicy};
 pub use crate::errors::{Error, ErrorKind, Result};
+pub use crate::metrics::{Metrics, NoMetrics};
 pub use crate::parameters::{Parameter, ParameterId, PARAMETERS};
-pub use crate::protocol::{CommandId, Request, Response};
-pub use crate::slip::SlipError;
+pub use crate::protocol::{CommandId, Request, RequestId, Response};
+pub use crate::slip::{Checksum, SlipError};
 pub use crate::types::{
-    ApsDataConfirm, ApsDataIndication, ApsDataRequest, ClusterId, Destination, DestinationAddress,
-    DeviceState, Endpoint, ExtendedAddress, NetworkState, Platform, ProfileId, SequenceId,
-    ShortAddress, SourceAddress, Version,
+    zdo_response_cluster, ApsDataConfirm, ApsDataIndication, ApsDataRequest, ApsDataRequestBuilder,
+    BroadcastAddress, ClusterId, ConfirmStatus, Destination, DestinationAddress, DeviceState,
+    Endpoint, ExtendedAddress, NetworkKey, NetworkState, NetworkStateChange, Platform, ProfileId,
+    SecurityMode, SequenceId, ShortAddress, SourceAddress, TxOptions, Version,
 };
 
+#[cfg(feature = "serial")]
 const BAUD: u32 = 38400;
 
+#[cfg(feature = "serial")]
 pub fn open_tty<P>(path: P) -> Result<(Deconz, ApsReader)>
+where
+    P: AsRef<Path>,
+{
+    open_tty_with_baud(path, BAUD)
+}
+
+/// Like `open_tty`, but with a non-default baud rate. Newer adapters (e.g. ConBee II) use 115200.
+#[cfg(feature = "serial")]
+pub fn open_tty_with_baud<P>(path: P, baud: u32) -> Result<(Deconz, ApsReader)>
+where
+    P: AsRef<Path>,
+{
+    open_tty_with_config(path, baud, DeconzConfig::default())
+}
+
+/// Like `open_tty_with_baud`, but with a non-default configuration. See `Deconz::with_config`.
+#[cfg(feature = "serial")]
+pub fn open_tty_with_config<P>(
+    path: P,
+    baud: u32,
+    config: DeconzConfig,
+) -> Result<(Deconz, ApsReader)>
+where
+    P: AsRef<Path>,
+{
+    let (reader, writer) = open_serial(path, baud)?;
+    Ok(Deconz::with_config(reader, writer, config))
+}
+
+/// Like `open_tty_with_config`, but re-opens the serial port at the same baud rate if it
+/// disconnects, keeping the returned handles usable. See `Deconz::new_reconnecting`.
+#[cfg(feature = "serial")]
+pub fn open_tty_reconnecting<P>(
+    path: P,
+    baud: u32,
+    config: DeconzConfig,
+    retry_policy: RetryPolicy,
+) -> Result<(Deconz, ApsReader)>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref().to_owned();
+    Deconz::new_reconnecting(move || open_serial(&path, baud), config, retry_policy)
+}
+
+#[cfg(feature = "serial")]
+fn open_serial<P>(path: P, baud: u32) -> Result<(ReadHalf<Serial>, WriteHalf<Serial>)>
 where
     P: AsRef<Path>,
 {
     let tty = Serial::from_path(
         path,
         &SerialPortSettings {
-            baud_rate: BAUD,
+            baud_rate: baud,
             timeout: std::time::Duration::from_secs(60),
             ..Default::default()
         },
     )?;
 
-    let (reader, writer) = tokio::io::split(tty);
-    Ok(Deconz::new(reader, writer))
+    Ok(tokio::io::split(tty))
 }
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
@@ -136,7 +193,7 @@ impl WriteWire for u32 {
     type Error = Error;
 
     fn wire_len(&self) -> u16 {
-        2
+        4
     }
 
     fn write_wire<W>(self, w: &mut W) -> Result<()>
@@ -175,6 +232,170 @@ impl WriteWire for u64 {
     }
 }
 
+impl ReadWire for i8 {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(r.read_i8()?)
+    }
+}
+
+impl WriteWire for i8 {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_i8(self)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for i16 {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(r.read_i16::<LittleEndian>()?)
+    }
+}
+
+impl WriteWire for i16 {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_i16::<LittleEndian>(self)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for i32 {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(r.read_i32::<LittleEndian>()?)
+    }
+}
+
+impl WriteWire for i32 {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        4
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_i32::<LittleEndian>(self)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for i64 {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(r.read_i64::<LittleEndian>()?)
+    }
+}
+
+impl WriteWire for i64 {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        8
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_i64::<LittleEndian>(self)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for bool {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(r.read_u8()? != 0)
+    }
+}
+
+impl WriteWire for bool {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_u8(u8::from(self))?;
+        Ok(())
+    }
+}
+
+impl<const N: usize> ReadWire for [u8; N] {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut bytes = [0; N];
+        r.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+impl<const N: usize> WriteWire for [u8; N] {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        N as u16
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(&self)?;
+        Ok(())
+    }
+}
+
 pub trait ReadWireExt {
     fn read_wire<T>(&mut self) -> StdResult<T, T::Error>
     where
@@ -210,3 +431,63 @@ where
         value.write_wire(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T>(value: T, expected: &[u8])
+    where
+        T: ReadWire<Error = Error> + WriteWire<Error = Error> + Copy + std::fmt::Debug + PartialEq,
+    {
+        let mut buffer = Vec::new();
+        buffer.write_wire(value).unwrap();
+        assert_eq!(buffer, expected, "{:?}", value);
+        assert_eq!(usize::from(value.wire_len()), expected.len(), "{:?}", value);
+        assert_eq!((&buffer[..]).read_wire::<T>().unwrap(), value);
+    }
+
+    #[test]
+    fn unsigned_wire_lens() {
+        assert_eq!(42u8.wire_len(), 1);
+        assert_eq!(42u16.wire_len(), 2);
+        assert_eq!(42u32.wire_len(), 4);
+        assert_eq!(42u64.wire_len(), 8);
+    }
+
+    #[test]
+    fn signed_round_trips() {
+        round_trip(-2i8, &[0xFE]);
+        round_trip(i8::MIN, &[0x80]);
+        round_trip(-2i16, &[0xFE, 0xFF]);
+        round_trip(i16::MIN, &[0x00, 0x80]);
+        round_trip(-2i32, &[0xFE, 0xFF, 0xFF, 0xFF]);
+        round_trip(i32::MAX, &[0xFF, 0xFF, 0xFF, 0x7F]);
+        round_trip(-2i64, &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        round_trip(i64::MIN, &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn bool_round_trips() {
+        round_trip(false, &[0x00]);
+        round_trip(true, &[0x01]);
+        assert!((&[0x02][..]).read_wire::<bool>().unwrap());
+    }
+
+    #[test]
+    fn truncated_signed_values_are_rejected() {
+        fn assert_truncated<T: ReadWire<Error = Error> + std::fmt::Debug>(bytes: &[u8]) {
+            match (&bytes[..]).read_wire::<T>() {
+                Err(Error {
+                    kind: ErrorKind::Io(error),
+                }) => assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof),
+                result => panic!("expected UnexpectedEof, got {:?}", result),
+            }
+        }
+
+        assert_truncated::<i8>(&[]);
+        assert_truncated::<i16>(&[0xFE]);
+        assert_truncated::<i32>(&[0xFE, 0xFF, 0xFF]);
+        assert_truncated::<i64>(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+}
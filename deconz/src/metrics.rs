@@ -0,0 +1,24 @@
+/// Notified of events on the link to the adapter, e.g. to count how often frames are dropped.
+///
+/// Every method does nothing by default, so implementations need only override the events they
+/// are interested in. Methods are called from the tasks driving the link, so should not block.
+pub trait Metrics: Send + Sync {
+    /// A frame was written to the adapter.
+    fn frame_sent(&self) {}
+    /// A well-formed frame was read from the adapter.
+    fn frame_received(&self) {}
+    /// A frame was discarded because its checksum didn't match.
+    fn crc_mismatch(&self) {}
+    /// A frame was discarded because it contained an invalid SLIP escape sequence.
+    fn invalid_escape(&self) {}
+    /// The adapter didn't respond to a request in time.
+    fn request_timeout(&self) {}
+    /// An ApsDataIndication was discarded because its `ApsReader` wasn't keeping up.
+    fn indication_dropped(&self) {}
+}
+
+/// Ignores every event. The default for `DeconzConfig::metrics`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
@@ -1,18 +1,21 @@
 use std::fmt::{self, Display};
 use std::io::{Read, Write};
 
-use crate::{Error, ErrorKind, ReadWire, ReadWireExt, Result, WriteWire, WriteWireExt};
+use crate::{
+    Error, ErrorKind, NetworkKey, ReadWire, ReadWireExt, Result, SecurityMode, WriteWire,
+    WriteWireExt,
+};
 
 macro_rules! define_parameters {
     ($(($param:ident, $id:expr, $ty:ty)),+ $(,)?) => {
         pub const PARAMETERS: &[ParameterId] = &[$(ParameterId::$param),+];
 
-        #[derive(Copy, Clone, Debug)]
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
         pub enum ParameterId {
             $($param),+
         }
 
-        #[derive(Copy, Clone, Debug)]
+        #[derive(Copy, Clone, Debug, Eq, PartialEq)]
         pub enum Parameter {
             $($param($ty)),+
         }
@@ -30,7 +33,7 @@ macro_rules! define_parameters {
 
             fn wire_len(&self) -> u16 {
                 match self {
-                    $(Parameter::$param(_) => std::mem::size_of::<$ty>() as u16),+
+                    $(Parameter::$param(value) => value.wire_len()),+
                 }
             }
 
@@ -117,10 +120,14 @@ define_parameters! {
     (ChannelMask, 0x0A, u32),
     (ApsExtendedPanId, 0x0B, u64),
     (TrustCenterAddress, 0x0E, u64),
-    (SecurityMode, 0x10, u8),
-    (NetworkKey, 0x18, u8),
+    (SecurityMode, 0x10, SecurityMode),
+    (PredefinedNwkPanId, 0x15, bool),
+    (NetworkKey, 0x18, NetworkKey),
     (CurrentChannel, 0x1C, u8),
+    (PermitJoin, 0x21, u8),
     (ProtocolVersion, 0x22, u16),
     (NwkUpdateId, 0x24, u8),
     (WatchdogTtl, 0x26, u32),
+    (NwkFrameCounter, 0x27, u32),
+    (AppZdpResponseHandling, 0x29, u16),
 }
@@ -3,14 +3,25 @@ use std::fmt::{self, Display};
 use std::io::{Cursor, Read, Write};
 
 use crate::{
-    ApsDataConfirm, ApsDataIndication, ApsDataRequest, Destination, DestinationAddress,
-    DeviceState, NetworkState, Parameter, ParameterId, Platform, ReadWire, SequenceId,
-    SourceAddress, Version, WriteWire,
+    ApsDataConfirm, ApsDataIndication, ApsDataRequest, BroadcastAddress, ConfirmStatus,
+    Destination, DestinationAddress, DeviceState, NetworkKey, NetworkState, Parameter, ParameterId,
+    Platform, ReadWire, SecurityMode, SequenceId, ShortAddress, SourceAddress, Version, WriteWire,
 };
 use crate::{Error, ErrorKind, ReadWireExt, Result, WriteWireExt};
 
 const HEADER_LEN: u16 = 5;
 
+/// The bytes in an ApsDataRequest's payload besides the destination and ASDU: the request ID,
+/// flags, profile, cluster, source endpoint, ASDU length, TX options and radius.
+const APS_DATA_REQUEST_LEN: u16 = 11;
+
+/// The longest ASDU that can be sent to `destination` before the frame's length fields overflow.
+pub(crate) fn max_frame_asdu_len(destination: &Destination) -> usize {
+    // The frame's header and payload length come before the ApsDataRequest.
+    let overhead = HEADER_LEN + 2 + APS_DATA_REQUEST_LEN + destination.wire_len();
+    usize::from(u16::MAX - overhead)
+}
+
 impl ReadWire for Platform {
     type Error = Error;
 
@@ -30,6 +41,93 @@ impl ReadWire for Platform {
     }
 }
 
+impl WriteWire for Platform {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let byte: u8 = match self {
+            Platform::Avr => 0x05,
+            Platform::Arm => 0x07,
+            Platform::Unknown(unknown) => unknown,
+        };
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for SecurityMode {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        match u8::read_wire(r)? {
+            0x0 => Ok(SecurityMode::NoSecurity),
+            0x1 => Ok(SecurityMode::PreconfiguredNetworkKey),
+            0x2 => Ok(SecurityMode::NetworkKeyFromTrustCenter),
+            0x3 => Ok(SecurityMode::NoMasterButTrustCenterLinkKey),
+            unknown => Err(ErrorKind::UnsupportedSecurityMode(unknown).into()),
+        }
+    }
+}
+
+impl WriteWire for SecurityMode {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let byte: u8 = match self {
+            SecurityMode::NoSecurity => 0x0,
+            SecurityMode::PreconfiguredNetworkKey => 0x1,
+            SecurityMode::NetworkKeyFromTrustCenter => 0x2,
+            SecurityMode::NoMasterButTrustCenterLinkKey => 0x3,
+        };
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for NetworkKey {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(NetworkKey(r.read_wire()?))
+    }
+}
+
+impl WriteWire for NetworkKey {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        self.0.wire_len()
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.0)?;
+        Ok(())
+    }
+}
+
 impl ReadWire for Version {
     type Error = Error;
 
@@ -44,6 +142,23 @@ impl ReadWire for Version {
     }
 }
 
+impl WriteWire for Version {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.minor)?;
+        w.write_wire(self.major)?;
+        Ok(())
+    }
+}
+
 impl ReadWire for DeviceState {
     type Error = Error;
 
@@ -75,6 +190,126 @@ impl ReadWire for DeviceState {
     }
 }
 
+impl WriteWire for DeviceState {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut byte: u8 = match self.network_state {
+            NetworkState::Offline => 0x0,
+            NetworkState::Joining => 0x1,
+            NetworkState::Connected => 0x2,
+            NetworkState::Leaving => 0x3,
+        };
+        if self.data_confirm {
+            byte |= 0b100;
+        }
+        if self.data_indication {
+            byte |= 0b1000;
+        }
+        if self.configuration_changed {
+            byte |= 0b10000;
+        }
+        if self.data_request_free_slots {
+            byte |= 0b100000;
+        }
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
+
+impl WriteWire for NetworkState {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let byte: u8 = match self {
+            NetworkState::Offline => 0x0,
+            NetworkState::Joining => 0x1,
+            NetworkState::Connected => 0x2,
+            NetworkState::Leaving => 0x3,
+        };
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
+
+impl ReadWire for ConfirmStatus {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = match u8::read_wire(r)? {
+            0x00 => ConfirmStatus::Success,
+            0xA7 => ConfirmStatus::NoAck,
+            0xD0 => ConfirmStatus::NoRoute,
+            0xE9 => ConfirmStatus::MacNoAck,
+            0xF0 => ConfirmStatus::MacTransactionExpired,
+            unknown => ConfirmStatus::Unknown(unknown),
+        };
+        Ok(status)
+    }
+}
+
+impl WriteWire for ConfirmStatus {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let byte: u8 = match self {
+            ConfirmStatus::Success => 0x00,
+            ConfirmStatus::NoAck => 0xA7,
+            ConfirmStatus::NoRoute => 0xD0,
+            ConfirmStatus::MacNoAck => 0xE9,
+            ConfirmStatus::MacTransactionExpired => 0xF0,
+            ConfirmStatus::Unknown(unknown) => unknown,
+        };
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
+
+impl WriteWire for BroadcastAddress {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let addr: u16 = match self {
+            BroadcastAddress::All => 0xFFFF,
+            BroadcastAddress::RxOnWhenIdle => 0xFFFD,
+            BroadcastAddress::Routers => 0xFFFC,
+        };
+        w.write_wire(addr)?;
+        Ok(())
+    }
+}
+
 impl ReadWire for Destination {
     type Error = Error;
     fn read_wire<R>(r: &mut R) -> Result<Self>
@@ -86,14 +321,25 @@ impl ReadWire for Destination {
             0x2 => {
                 let short_address = r.read_wire()?;
                 let endpoint = r.read_wire()?;
-                Ok(Destination::Nwk(short_address, endpoint))
+                // Report broadcasts the way they were requested.
+                let destination = match short_address {
+                    ShortAddress(0xFFFF) => Destination::Broadcast(BroadcastAddress::All, endpoint),
+                    ShortAddress(0xFFFD) => {
+                        Destination::Broadcast(BroadcastAddress::RxOnWhenIdle, endpoint)
+                    }
+                    ShortAddress(0xFFFC) => {
+                        Destination::Broadcast(BroadcastAddress::Routers, endpoint)
+                    }
+                    short_address => Destination::Nwk(short_address, endpoint),
+                };
+                Ok(destination)
             }
             0x3 => {
                 let extended_address = r.read_wire()?;
                 let endpoint = r.read_wire()?;
                 Ok(Destination::Ieee(extended_address, endpoint))
             }
-            _ => unreachable!("invalid address mode"),
+            unknown => Err(ErrorKind::UnsupportedAddressMode(unknown).into()),
         }
     }
 }
@@ -103,9 +349,10 @@ impl WriteWire for Destination {
 
     fn wire_len(&self) -> u16 {
         match self {
-            Destination::Group(_) => 2,
-            Destination::Nwk(_, _) => 3,
-            Destination::Ieee(_, _) => 9,
+            // Address mode, address and endpoint (if present)
+            Destination::Group(_) => 3,
+            Destination::Nwk(_, _) | Destination::Broadcast(_, _) => 4,
+            Destination::Ieee(_, _) => 10,
         }
     }
 
@@ -116,7 +363,7 @@ impl WriteWire for Destination {
         // Address mode
         let address_mode: u8 = match self {
             Destination::Group(_) => 0x1,
-            Destination::Nwk(_, _) => 0x2,
+            Destination::Nwk(_, _) | Destination::Broadcast(_, _) => 0x2,
             Destination::Ieee(_, _) => 0x3,
         };
         w.write_wire(address_mode)?;
@@ -129,12 +376,17 @@ impl WriteWire for Destination {
             Destination::Ieee(addr, _) => {
                 w.write_wire(addr)?;
             }
+            Destination::Broadcast(addr, _) => {
+                w.write_wire(addr)?;
+            }
         };
 
         // Endpoint
         match self {
             Destination::Group(_) => {}
-            Destination::Nwk(_, endpoint) | Destination::Ieee(_, endpoint) => {
+            Destination::Nwk(_, endpoint)
+            | Destination::Ieee(_, endpoint)
+            | Destination::Broadcast(_, endpoint) => {
                 w.write_wire(endpoint)?;
             }
         }
@@ -145,12 +397,13 @@ impl WriteWire for Destination {
 
 pub type RequestId = u8;
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum CommandId {
     Version,
     ReadParameter,
     WriteParameter,
     DeviceState,
+    ChangeNetworkState,
     DeviceStateChanged,
     ApsDataIndication,
     ApsDataRequest,
@@ -164,7 +417,7 @@ impl CommandId {
     /// Whether a response of this kind was solicited by a request.
     pub fn solicited(&self) -> bool {
         match self {
-            CommandId::DeviceStateChanged => false,
+            CommandId::DeviceStateChanged | CommandId::MacPoll => false,
             _ => true,
         }
     }
@@ -177,6 +430,7 @@ impl From<CommandId> for u8 {
             CommandId::ReadParameter => 0x0A,
             CommandId::WriteParameter => 0x0B,
             CommandId::DeviceState => 0x07,
+            CommandId::ChangeNetworkState => 0x08,
             CommandId::DeviceStateChanged => 0x0E,
             CommandId::ApsDataIndication => 0x17,
             CommandId::ApsDataRequest => 0x12,
@@ -195,6 +449,7 @@ impl TryFrom<u8> for CommandId {
             0x0A => Ok(CommandId::ReadParameter),
             0x0B => Ok(CommandId::WriteParameter),
             0x07 => Ok(CommandId::DeviceState),
+            0x08 => Ok(CommandId::ChangeNetworkState),
             0x0E => Ok(CommandId::DeviceStateChanged),
             0x1C => Ok(CommandId::MacPoll),
             0x17 => Ok(CommandId::ApsDataIndication),
@@ -242,6 +497,7 @@ impl Display for CommandId {
             CommandId::ReadParameter => write!(f, "ReadParameter ({})", u8::from(*self)),
             CommandId::WriteParameter => write!(f, "WriteParameter ({})", u8::from(*self)),
             CommandId::DeviceState => write!(f, "DeviceState ({})", u8::from(*self)),
+            CommandId::ChangeNetworkState => write!(f, "ChangeNetworkState ({})", u8::from(*self)),
             CommandId::DeviceStateChanged => write!(f, "DeviceStateChanged ({})", u8::from(*self)),
             CommandId::MacPoll => write!(f, "MacPoll ({})", u8::from(*self)),
             CommandId::ApsDataIndication => write!(f, "ApsDataIndication ({})", u8::from(*self)),
@@ -251,12 +507,13 @@ impl Display for CommandId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Request {
     Version,
     ReadParameter { parameter_id: ParameterId },
     WriteParameter { parameter: Parameter },
     DeviceState,
+    ChangeNetworkState(NetworkState),
     ApsDataIndication,
     ApsDataRequest(RequestId, ApsDataRequest),
     ApsDataConfirm,
@@ -269,6 +526,7 @@ impl Request {
             Request::ReadParameter { .. } => CommandId::ReadParameter,
             Request::WriteParameter { .. } => CommandId::WriteParameter,
             Request::DeviceState => CommandId::DeviceState,
+            Request::ChangeNetworkState(_) => CommandId::ChangeNetworkState,
             Request::ApsDataIndication => CommandId::ApsDataIndication,
             Request::ApsDataRequest(_, _) => CommandId::ApsDataRequest,
             Request::ApsDataConfirm => CommandId::ApsDataConfirm,
@@ -281,18 +539,27 @@ impl Request {
             Request::ReadParameter { .. } => Some(1),
             Request::WriteParameter { parameter } => Some(1 + parameter.wire_len()),
             Request::DeviceState => None,
+            Request::ChangeNetworkState(_) => None,
             Request::ApsDataIndication => Some(1),
             Request::ApsDataRequest(
                 _,
                 ApsDataRequest {
                     destination, asdu, ..
                 },
-            ) => Some(12 + destination.wire_len() + (asdu.len() as u16)),
+            ) => Some(APS_DATA_REQUEST_LEN + destination.wire_len() + (asdu.len() as u16)),
             // Include payload len even though it is zero:
             Request::ApsDataConfirm => Some(0),
         }
     }
 
+    /// Length of any payload that isn't preceded by a 2-byte payload length.
+    fn unprefixed_payload_len(&self) -> u16 {
+        match self {
+            Request::ChangeNetworkState(network_state) => network_state.wire_len(),
+            _ => 0,
+        }
+    }
+
     fn write_payload(self, buffer: &mut Vec<u8>) -> Result<()> {
         match self {
             Request::Version => {}
@@ -304,6 +571,9 @@ impl Request {
                 buffer.write_wire(parameter)?;
             }
             Request::DeviceState => {}
+            Request::ChangeNetworkState(network_state) => {
+                buffer.write_wire(network_state)?;
+            }
             Request::ApsDataIndication => {
                 buffer.write_wire(4 as u8)?;
             }
@@ -315,6 +585,8 @@ impl Request {
                     cluster_id,
                     source_endpoint,
                     asdu,
+                    tx_options,
+                    radius,
                 },
             ) => {
                 buffer.write_wire(request_id)?;
@@ -325,8 +597,8 @@ impl Request {
                 buffer.write_wire(source_endpoint)?;
                 buffer.write_wire(asdu.len() as u16)?;
                 buffer.extend(asdu);
-                buffer.write_wire(0x04 as u8)?; // tx options, use aps acks
-                buffer.write_wire(0 as u8)?; // radius, infinite hops
+                buffer.write_wire(tx_options.bits())?;
+                buffer.write_wire(radius)?;
             }
             Request::ApsDataConfirm => {}
         }
@@ -338,7 +610,7 @@ impl Request {
 impl Request {
     pub fn into_frame(self, sequence_id: SequenceId) -> Result<Vec<u8>> {
         let payload_len = self.payload_len();
-        let mut frame_len = HEADER_LEN;
+        let mut frame_len = HEADER_LEN + self.unprefixed_payload_len();
         if let Some(payload_len) = payload_len {
             // Only include 2-byte payload length when there is a payload:
             // 2 byte payload len:
@@ -363,7 +635,7 @@ impl Request {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Response {
     Version {
         version: Version,
@@ -372,6 +644,7 @@ pub enum Response {
     Parameter(Parameter),
     WriteParameter(ParameterId),
     DeviceState(DeviceState),
+    ChangeNetworkState(DeviceState),
     DeviceStateChanged(DeviceState),
     ApsDataIndication {
         device_state: DeviceState,
@@ -387,7 +660,7 @@ pub enum Response {
         aps_data_confirm: ApsDataConfirm,
     },
     MacPoll {
-        address: u16,
+        address: ShortAddress,
     },
 }
 
@@ -398,6 +671,7 @@ impl Response {
             Response::Parameter(_) => CommandId::ReadParameter,
             Response::WriteParameter(_) => CommandId::WriteParameter,
             Response::DeviceState(_) => CommandId::DeviceState,
+            Response::ChangeNetworkState(_) => CommandId::ChangeNetworkState,
             Response::DeviceStateChanged(_) => CommandId::DeviceStateChanged,
             Response::ApsDataIndication { .. } => CommandId::ApsDataIndication,
             Response::ApsDataRequest { .. } => CommandId::ApsDataRequest,
@@ -420,14 +694,15 @@ impl Response {
         }
     }
 
-    pub fn from_frame(frame: Vec<u8>) -> Result<Self> {
+    pub fn from_frame(frame: &[u8]) -> Result<Self> {
         let len = frame.len();
         let mut frame = Cursor::new(frame);
 
         let command_id = frame.read_wire()?;
         let _sequence_id: u8 = frame.read_wire()?;
 
-        let _reserved: u8 = frame.read_wire()?;
+        // The firmware reports whether it accepted the request here (0x00 is success).
+        let status: u8 = frame.read_wire()?;
 
         let header_len: usize = HEADER_LEN.into();
         let frame_len: u16 = frame.read_wire()?;
@@ -456,6 +731,9 @@ impl Response {
                 let _payload_len: u16 = payload.read_wire()?;
 
                 let parameter_id = payload.read_wire()?;
+                if status != 0x00 {
+                    return Err(ErrorKind::ParameterWriteRejected(parameter_id).into());
+                }
 
                 Response::WriteParameter(parameter_id)
             }
@@ -464,6 +742,11 @@ impl Response {
 
                 Response::DeviceState(device_state)
             }
+            CommandId::ChangeNetworkState => {
+                let device_state = payload.read_wire()?;
+
+                Response::ChangeNetworkState(device_state)
+            }
             CommandId::DeviceStateChanged => {
                 let device_state = payload.read_wire()?;
 
@@ -477,7 +760,7 @@ impl Response {
                     0x1 => DestinationAddress::Group(payload.read_wire()?),
                     0x2 => DestinationAddress::Nwk(payload.read_wire()?),
                     0x3 => DestinationAddress::Ieee(payload.read_wire()?),
-                    _ => unimplemented!("unknown destination address mode"),
+                    unknown => return Err(ErrorKind::UnsupportedAddressMode(unknown).into()),
                 };
                 let destination_endpoint = payload.read_wire()?;
 
@@ -487,7 +770,7 @@ impl Response {
                         let extended = payload.read_wire()?;
                         SourceAddress { short, extended }
                     }
-                    _ => unimplemented!("unknown source address mode "),
+                    unknown => return Err(ErrorKind::UnsupportedAddressMode(unknown).into()),
                 };
                 let source_endpoint = payload.read_wire()?;
 
@@ -496,7 +779,7 @@ impl Response {
 
                 let asdu_length: u16 = payload.read_wire()?;
                 let mut asdu = vec![0; asdu_length.into()];
-                payload.read(&mut asdu)?;
+                payload.read_exact(&mut asdu)?;
 
                 let aps_data_indication = ApsDataIndication {
                     destination_address,
@@ -526,9 +809,11 @@ impl Response {
             }
             CommandId::MacPoll => {
                 let _payload_len: u16 = payload.read_wire()?;
-                let _enum: u8 = payload.read_wire()?;
 
-                let address = payload.read_wire()?;
+                let address = match u8::read_wire(&mut payload)? {
+                    0x2 => payload.read_wire()?,
+                    unknown => return Err(ErrorKind::UnsupportedAddressMode(unknown).into()),
+                };
 
                 Response::MacPoll { address }
             }
@@ -557,4 +842,517 @@ impl Response {
 
         Ok(response)
     }
+
+    /// Encodes the response as the adapter would send it. Only needed to play the part of the
+    /// adapter, as `testing::MockAdapter` does.
+    #[cfg(any(test, feature = "testing"))]
+    pub fn into_frame(self, sequence_id: SequenceId) -> Result<Vec<u8>> {
+        let command_id = self.command_id();
+
+        // Everything but the fixed-length responses is preceded by a 2-byte payload length.
+        let mut payload = Vec::new();
+        let mut prefixed = Vec::new();
+        match self {
+            Response::Version { version, platform } => {
+                payload.write_wire(platform)?;
+                payload.write_wire(version)?;
+            }
+            Response::Parameter(parameter) => {
+                prefixed.write_wire(parameter.id())?;
+                prefixed.write_wire(parameter)?;
+            }
+            Response::WriteParameter(parameter_id) => {
+                prefixed.write_wire(parameter_id)?;
+            }
+            Response::DeviceState(device_state)
+            | Response::ChangeNetworkState(device_state)
+            | Response::DeviceStateChanged(device_state) => {
+                payload.write_wire(device_state)?;
+            }
+            Response::ApsDataIndication {
+                device_state,
+                aps_data_indication,
+            } => {
+                prefixed.write_wire(device_state)?;
+                match aps_data_indication.destination_address {
+                    DestinationAddress::Group(addr) => {
+                        prefixed.write_wire(0x1_u8)?;
+                        prefixed.write_wire(addr)?;
+                    }
+                    DestinationAddress::Nwk(addr) => {
+                        prefixed.write_wire(0x2_u8)?;
+                        prefixed.write_wire(addr)?;
+                    }
+                    DestinationAddress::Ieee(addr) => {
+                        prefixed.write_wire(0x3_u8)?;
+                        prefixed.write_wire(addr)?;
+                    }
+                }
+                prefixed.write_wire(aps_data_indication.destination_endpoint)?;
+                prefixed.write_wire(0x4_u8)?;
+                prefixed.write_wire(aps_data_indication.source_address.short)?;
+                prefixed.write_wire(aps_data_indication.source_address.extended)?;
+                prefixed.write_wire(aps_data_indication.source_endpoint)?;
+                prefixed.write_wire(aps_data_indication.profile_id)?;
+                prefixed.write_wire(aps_data_indication.cluster_id)?;
+                prefixed.write_wire(aps_data_indication.asdu.len() as u16)?;
+                prefixed.extend(aps_data_indication.asdu);
+            }
+            Response::ApsDataRequest {
+                device_state,
+                request_id,
+            } => {
+                prefixed.write_wire(device_state)?;
+                prefixed.write_wire(request_id)?;
+            }
+            Response::ApsDataConfirm {
+                device_state,
+                request_id,
+                aps_data_confirm,
+            } => {
+                prefixed.write_wire(device_state)?;
+                prefixed.write_wire(request_id)?;
+                prefixed.write_wire(aps_data_confirm.destination)?;
+                prefixed.write_wire(aps_data_confirm.source_endpoint)?;
+                prefixed.write_wire(aps_data_confirm.status)?;
+            }
+            Response::MacPoll { address } => {
+                prefixed.write_wire(0x2_u8)?;
+                prefixed.write_wire(address)?;
+            }
+        }
+        if !prefixed.is_empty() {
+            payload.write_wire(prefixed.len() as u16)?;
+            payload.extend(prefixed);
+        }
+
+        let frame_len = HEADER_LEN + payload.len() as u16;
+        let mut buffer = Vec::with_capacity(usize::from(frame_len));
+        buffer.write_wire(command_id)?;
+        buffer.write_wire(sequence_id)?;
+        buffer.write_wire(0x00_u8)?; // status
+        buffer.write_wire(frame_len)?;
+        buffer.extend(payload);
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClusterId, Endpoint, ExtendedAddress, ProfileId};
+
+    const SEQ: SequenceId = 0x2A;
+    const REQUEST_ID: RequestId = 0x07;
+
+    /// Connected, with free slots for more ApsDataRequests.
+    const CONNECTED: DeviceState = DeviceState {
+        network_state: NetworkState::Connected,
+        data_confirm: false,
+        data_indication: false,
+        data_request_free_slots: true,
+        configuration_changed: false,
+    };
+
+    fn aps_data_request() -> ApsDataRequest {
+        ApsDataRequest::builder()
+            .destination(Destination::Nwk(ShortAddress(0x1234), Endpoint(1)))
+            .cluster(ClusterId::ON_OFF)
+            .asdu(vec![0x01, 0x00, 0x02])
+            .build()
+            .unwrap()
+    }
+
+    fn aps_data_indication(destination_address: DestinationAddress) -> ApsDataIndication {
+        ApsDataIndication {
+            destination_address,
+            destination_endpoint: Endpoint(1),
+            source_address: SourceAddress {
+                short: ShortAddress(0x1234),
+                extended: ExtendedAddress(0x00212effff012345),
+            },
+            source_endpoint: Endpoint(1),
+            profile_id: ProfileId::HA,
+            cluster_id: ClusterId::ON_OFF,
+            asdu: vec![0x18, 0x01, 0x0B],
+        }
+    }
+
+    #[test]
+    fn request_frames() {
+        let cases = vec![
+            (Request::Version, vec![0x0D, SEQ, 0x00, 0x05, 0x00]),
+            (
+                Request::ReadParameter {
+                    parameter_id: ParameterId::MacAddress,
+                },
+                vec![0x0A, SEQ, 0x00, 0x08, 0x00, 0x01, 0x00, 0x01],
+            ),
+            (
+                Request::WriteParameter {
+                    parameter: Parameter::NwkPanId(0x1234),
+                },
+                vec![0x0B, SEQ, 0x00, 0x0A, 0x00, 0x03, 0x00, 0x05, 0x34, 0x12],
+            ),
+            (Request::DeviceState, vec![0x07, SEQ, 0x00, 0x05, 0x00]),
+            (
+                Request::ChangeNetworkState(NetworkState::Connected),
+                vec![0x08, SEQ, 0x00, 0x06, 0x00, 0x02],
+            ),
+            (
+                Request::ApsDataIndication,
+                vec![0x17, SEQ, 0x00, 0x08, 0x00, 0x01, 0x00, 0x04],
+            ),
+            (
+                Request::ApsDataRequest(REQUEST_ID, aps_data_request()),
+                vec![
+                    0x12, SEQ, 0x00, 0x19, 0x00, 0x12, 0x00, REQUEST_ID, 0x00, 0x02, 0x34, 0x12,
+                    0x01, 0x04, 0x01, 0x06, 0x00, 0x01, 0x03, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00,
+                ],
+            ),
+            (
+                Request::ApsDataConfirm,
+                vec![0x04, SEQ, 0x00, 0x07, 0x00, 0x00, 0x00],
+            ),
+        ];
+
+        for (request, expected) in cases {
+            let frame = request.clone().into_frame(SEQ).unwrap();
+            assert_eq!(frame, expected, "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn destination_round_trips() {
+        let cases = vec![
+            (
+                Destination::Group(ShortAddress(0x0001)),
+                vec![0x01, 0x01, 0x00],
+            ),
+            (
+                Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+                vec![0x02, 0x34, 0x12, 0x01],
+            ),
+            (
+                Destination::Ieee(ExtendedAddress(0x00212effff012345), Endpoint(1)),
+                vec![0x03, 0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00, 0x01],
+            ),
+            (
+                Destination::Broadcast(BroadcastAddress::All, Endpoint(0xFF)),
+                vec![0x02, 0xFF, 0xFF, 0xFF],
+            ),
+            (
+                Destination::Broadcast(BroadcastAddress::RxOnWhenIdle, Endpoint(0)),
+                vec![0x02, 0xFD, 0xFF, 0x00],
+            ),
+            (
+                Destination::Broadcast(BroadcastAddress::Routers, Endpoint(0)),
+                vec![0x02, 0xFC, 0xFF, 0x00],
+            ),
+        ];
+
+        for (destination, expected) in cases {
+            let mut buffer = Vec::new();
+            buffer.write_wire(destination).unwrap();
+            assert_eq!(buffer, expected, "{:?}", destination);
+            assert_eq!(usize::from(destination.wire_len()), expected.len());
+            assert_eq!(
+                Destination::read_wire(&mut &buffer[..]).unwrap(),
+                destination
+            );
+        }
+
+        // Broadcast addresses are reported as broadcasts, even if sent as a plain NWK address.
+        let nwk = Destination::Nwk(ShortAddress(0xFFFC), Endpoint(0));
+        let mut buffer = Vec::new();
+        buffer.write_wire(nwk).unwrap();
+        assert_eq!(
+            Destination::read_wire(&mut &buffer[..]).unwrap(),
+            Destination::Broadcast(BroadcastAddress::Routers, Endpoint(0))
+        );
+    }
+
+    #[test]
+    fn group_cast_confirm() {
+        // A group-cast has no destination endpoint, so the source endpoint follows the group.
+        let frame = [
+            0x04, SEQ, 0x00, 0x12, 0x00, 0x0B, 0x00, 0x22, REQUEST_ID, 0x01, 0x34, 0x12, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            Response::from_frame(&frame).unwrap(),
+            Response::ApsDataConfirm {
+                device_state: CONNECTED,
+                request_id: REQUEST_ID,
+                aps_data_confirm: ApsDataConfirm {
+                    destination: Destination::Group(ShortAddress(0x1234)),
+                    source_endpoint: Endpoint(1),
+                    status: ConfirmStatus::Success,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn indication_with_unsupported_address_mode() {
+        // The same indication as in `captured_frames`, with a destination address mode of 0x09.
+        let frame = [
+            0x17, SEQ, 0x00, 0x25, 0x00, 0x1E, 0x00, 0x22, 0x09, 0x00, 0x00, 0x01, 0x04, 0x34,
+            0x12, 0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00, 0x01, 0x04, 0x01, 0x06, 0x00,
+            0x03, 0x00, 0x18, 0x01, 0x0B, 0x00, 0x00, 0xFF, 0xC5,
+        ];
+        match Response::from_frame(&frame) {
+            Err(Error {
+                kind: ErrorKind::UnsupportedAddressMode(0x09),
+            }) => {}
+            result => panic!("expected UnsupportedAddressMode, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn aps_data_request_frame_lengths() {
+        // Request ID, flags, profile, cluster, source endpoint, ASDU length, TX options and radius.
+        assert_eq!(APS_DATA_REQUEST_LEN, 1 + 1 + 2 + 2 + 1 + 2 + 1 + 1);
+
+        let asdu = vec![0xAA; 5];
+        for &(destination, wire_len) in &[
+            (Destination::Group(ShortAddress(0x0001)), 3),
+            (Destination::Nwk(ShortAddress(0x1234), Endpoint(1)), 4),
+            (
+                Destination::Broadcast(BroadcastAddress::All, Endpoint(0xFF)),
+                4,
+            ),
+            (
+                Destination::Ieee(ExtendedAddress(0x00212effff012345), Endpoint(1)),
+                10,
+            ),
+        ] {
+            assert_eq!(destination.wire_len(), wire_len);
+
+            let request = ApsDataRequest::builder()
+                .destination(destination)
+                .cluster(ClusterId::ON_OFF)
+                .asdu(asdu.clone())
+                .build()
+                .unwrap();
+            let frame = Request::ApsDataRequest(REQUEST_ID, request)
+                .into_frame(SEQ)
+                .unwrap();
+
+            let payload_len = APS_DATA_REQUEST_LEN + wire_len + asdu.len() as u16;
+            assert_eq!(&frame[3..5], &(frame.len() as u16).to_le_bytes());
+            assert_eq!(&frame[5..7], &payload_len.to_le_bytes());
+            assert_eq!(frame.len(), usize::from(HEADER_LEN + 2 + payload_len));
+        }
+    }
+
+    #[test]
+    fn write_channel_mask_frame() {
+        let frame = Request::WriteParameter {
+            parameter: Parameter::ChannelMask(0x0200_0000),
+        }
+        .into_frame(SEQ)
+        .unwrap();
+        assert_eq!(
+            frame,
+            vec![0x0B, SEQ, 0x00, 0x0C, 0x00, 0x05, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x02]
+        );
+    }
+
+    #[test]
+    fn write_network_key_frame() {
+        let key = [
+            0x01, 0x03, 0x05, 0x07, 0x09, 0x0B, 0x0D, 0x0F, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0A,
+            0x0C, 0x0E,
+        ];
+        let parameter = Parameter::NetworkKey(NetworkKey(key));
+        assert_eq!(parameter.wire_len(), 16);
+
+        let frame = Request::WriteParameter { parameter }
+            .into_frame(SEQ)
+            .unwrap();
+        // The frame's length, the payload's length, then the parameter ID and key.
+        assert_eq!(frame.len(), 24);
+        assert_eq!(&frame[3..5], &[0x18, 0x00]);
+        assert_eq!(&frame[5..7], &[0x11, 0x00]);
+        assert_eq!(frame[7], 0x18);
+        assert_eq!(&frame[8..], &key);
+    }
+
+    #[test]
+    fn response_round_trips() {
+        let mut responses = vec![
+            Response::Version {
+                version: Version {
+                    major: 0x26,
+                    minor: 0x40,
+                },
+                platform: Platform::Arm,
+            },
+            Response::Version {
+                version: Version {
+                    major: 0x26,
+                    minor: 0x50,
+                },
+                platform: Platform::Unknown(0x09),
+            },
+            Response::Parameter(Parameter::MacAddress(0x00212effff012345)),
+            Response::Parameter(Parameter::NwkPanId(0x1234)),
+            Response::Parameter(Parameter::ChannelMask(0x0200_0000)),
+            Response::Parameter(Parameter::SecurityMode(
+                SecurityMode::NoMasterButTrustCenterLinkKey,
+            )),
+            Response::Parameter(Parameter::PredefinedNwkPanId(true)),
+            Response::Parameter(Parameter::NetworkKey(NetworkKey([0xAB; 16]))),
+            Response::WriteParameter(ParameterId::PermitJoin),
+            Response::DeviceState(DeviceState::default()),
+            Response::ChangeNetworkState(DeviceState {
+                network_state: NetworkState::Leaving,
+                ..CONNECTED
+            }),
+            Response::DeviceStateChanged(DeviceState {
+                network_state: NetworkState::Joining,
+                data_confirm: true,
+                data_indication: true,
+                data_request_free_slots: false,
+                configuration_changed: true,
+            }),
+            Response::ApsDataRequest {
+                device_state: CONNECTED,
+                request_id: REQUEST_ID,
+            },
+            Response::MacPoll {
+                address: ShortAddress(0x1234),
+            },
+        ];
+        for &destination_address in &[
+            DestinationAddress::Group(ShortAddress(0x0001)),
+            DestinationAddress::Nwk(ShortAddress(0x0000)),
+            DestinationAddress::Ieee(ExtendedAddress(0x00212effff012345)),
+        ] {
+            responses.push(Response::ApsDataIndication {
+                device_state: CONNECTED,
+                aps_data_indication: aps_data_indication(destination_address),
+            });
+        }
+        for &(destination, status) in &[
+            (
+                Destination::Group(ShortAddress(0x0001)),
+                ConfirmStatus::Success,
+            ),
+            (
+                Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+                ConfirmStatus::NoAck,
+            ),
+            (
+                Destination::Ieee(ExtendedAddress(0x00212effff012345), Endpoint(1)),
+                ConfirmStatus::NoRoute,
+            ),
+            (
+                Destination::Broadcast(BroadcastAddress::Routers, Endpoint(0)),
+                ConfirmStatus::MacNoAck,
+            ),
+            (
+                Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+                ConfirmStatus::MacTransactionExpired,
+            ),
+            (
+                Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+                ConfirmStatus::Unknown(0xE1),
+            ),
+        ] {
+            responses.push(Response::ApsDataConfirm {
+                device_state: CONNECTED,
+                request_id: REQUEST_ID,
+                aps_data_confirm: ApsDataConfirm {
+                    destination,
+                    source_endpoint: Endpoint(1),
+                    status,
+                },
+            });
+        }
+
+        for response in responses {
+            let frame = response.clone().into_frame(SEQ).unwrap();
+            assert_eq!(frame[0], u8::from(response.command_id()));
+            assert_eq!(frame[1], SEQ);
+            assert_eq!(&frame[3..5], &(frame.len() as u16).to_le_bytes());
+            assert_eq!(Response::from_frame(&frame).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn captured_frames() {
+        let cases = vec![
+            (
+                vec![0x0E, SEQ, 0x00, 0x06, 0x00, 0x22],
+                Response::DeviceStateChanged(CONNECTED),
+            ),
+            (
+                vec![0x07, SEQ, 0x00, 0x08, 0x00, 0x22, 0x00, 0x00],
+                Response::DeviceState(CONNECTED),
+            ),
+            (
+                vec![0x0A, SEQ, 0x00, 0x0A, 0x00, 0x03, 0x00, 0x05, 0x34, 0x12],
+                Response::Parameter(Parameter::NwkPanId(0x1234)),
+            ),
+            (
+                vec![0x0B, SEQ, 0x00, 0x08, 0x00, 0x01, 0x00, 0x21],
+                Response::WriteParameter(ParameterId::PermitJoin),
+            ),
+            (
+                vec![0x12, SEQ, 0x00, 0x09, 0x00, 0x02, 0x00, 0x22, REQUEST_ID],
+                Response::ApsDataRequest {
+                    device_state: CONNECTED,
+                    request_id: REQUEST_ID,
+                },
+            ),
+            (
+                // Followed by four reserved bytes.
+                vec![
+                    0x04, SEQ, 0x00, 0x13, 0x00, 0x0C, 0x00, 0x22, REQUEST_ID, 0x02, 0x34, 0x12,
+                    0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+                ],
+                Response::ApsDataConfirm {
+                    device_state: CONNECTED,
+                    request_id: REQUEST_ID,
+                    aps_data_confirm: ApsDataConfirm {
+                        destination: Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+                        source_endpoint: Endpoint(1),
+                        status: ConfirmStatus::Success,
+                    },
+                },
+            ),
+            (
+                // Followed by two reserved bytes, the LQI and the RSSI.
+                vec![
+                    0x17, SEQ, 0x00, 0x25, 0x00, 0x1E, 0x00, 0x22, 0x02, 0x00, 0x00, 0x01, 0x04,
+                    0x34, 0x12, 0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00, 0x01, 0x04, 0x01,
+                    0x06, 0x00, 0x03, 0x00, 0x18, 0x01, 0x0B, 0x00, 0x00, 0xFF, 0xC5,
+                ],
+                Response::ApsDataIndication {
+                    device_state: CONNECTED,
+                    aps_data_indication: aps_data_indication(DestinationAddress::Nwk(
+                        ShortAddress(0x0000),
+                    )),
+                },
+            ),
+            (
+                vec![0x1C, SEQ, 0x00, 0x0A, 0x00, 0x03, 0x00, 0x02, 0x34, 0x12],
+                Response::MacPoll {
+                    address: ShortAddress(0x1234),
+                },
+            ),
+        ];
+
+        for (frame, expected) in cases {
+            assert_eq!(
+                Response::from_frame(&frame).unwrap(),
+                expected,
+                "{:02x?}",
+                frame
+            );
+        }
+    }
 }
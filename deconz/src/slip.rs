@@ -1,7 +1,8 @@
-use std::convert::TryInto;
 use std::fmt::{self, Display};
+use std::io;
+use std::time::Duration;
 
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufWriter};
 
 use crate::Result;
 
@@ -10,11 +11,20 @@ const ESC: u8 = 219;
 const ESC_END: u8 = 220;
 const ESC_ESC: u8 = 221;
 
+/// The command ID, sequence ID, status and frame length that start every frame, which the rest
+/// of the crate relies on being present.
+const HEADER_LEN: usize = 5;
+
+/// How many bytes `Reader` reads from the underlying reader at a time.
+const READ_BUFFER_LEN: usize = 1024;
+
 #[derive(Clone, Copy, Debug)]
 pub enum SlipError {
     MissingCrc,
     MismatchedCrc,
+    ShortFrame,
     InvalidEscape,
+    Timeout,
 }
 
 impl Display for SlipError {
@@ -22,61 +32,153 @@ impl Display for SlipError {
         match self {
             SlipError::MissingCrc => write!(f, "missing CRC"),
             SlipError::MismatchedCrc => write!(f, "mismatched CRC"),
+            SlipError::ShortFrame => write!(f, "frame shorter than header"),
             SlipError::InvalidEscape => write!(f, "invalid escape sequence"),
+            SlipError::Timeout => write!(f, "timed out part way through frame"),
         }
     }
 }
 
 impl std::error::Error for SlipError {}
 
+/// The integrity check appended to each SLIP frame.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Checksum {
+    /// The two's complement of the sum of all bytes in the frame, as used by the deCONZ firmware.
+    /// This is the only wire-compatible choice when talking to a real adapter.
+    #[default]
+    Sum,
+    /// CRC-16/CCITT-FALSE. Catches errors the summation can't (e.g. transposed bytes), but is only
+    /// useful when both ends of the link agree to use it.
+    Crc16,
+}
+
+impl Checksum {
+    fn calculate(self, data: &[u8]) -> u16 {
+        match self {
+            Checksum::Sum => sum16(data),
+            Checksum::Crc16 => crc16(data),
+        }
+    }
+}
+
 pub struct Reader<R>
 where
     R: AsyncRead + Unpin,
 {
-    inner: BufReader<R>,
+    inner: R,
+    checksum: Checksum,
+    timeout: Option<Duration>,
+    /// Bytes read from `inner` but not yet consumed, which are `buffer[pos..len]`.
+    buffer: Box<[u8]>,
+    pos: usize,
+    len: usize,
 }
 
 impl<R> Reader<R>
 where
     R: AsyncRead + Unpin,
 {
-    pub fn new(read: R) -> Self {
+    pub fn new(read: R, checksum: Checksum) -> Self {
+        Self {
+            inner: read,
+            checksum,
+            timeout: None,
+            buffer: vec![0; READ_BUFFER_LEN].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Like `new`, but gives up on a frame if the next byte doesn't arrive within `timeout`. The
+    /// timeout doesn't apply while waiting for a frame to start.
+    pub fn new_with_timeout(read: R, checksum: Checksum, timeout: Duration) -> Self {
         Self {
-            inner: BufReader::new(read),
+            timeout: Some(timeout),
+            ..Self::new(read, checksum)
+        }
+    }
+
+    /// Takes the next byte from the buffer, if there is one.
+    fn buffered_byte(&mut self) -> Option<u8> {
+        if self.pos == self.len {
+            return None;
         }
+        let byte = self.buffer[self.pos];
+        self.pos += 1;
+        Some(byte)
     }
 
     async fn read_byte(&mut self) -> Result<u8> {
-        let mut buf = [0; 1];
-        self.inner.read(&mut buf).await?;
-        Ok(buf[0])
+        if let Some(byte) = self.buffered_byte() {
+            return Ok(byte);
+        }
+
+        // A zero-length read means the serial port has gone away, which is reported as
+        // `UnexpectedEof`, as `read_exact` would.
+        let len = self.inner.read(&mut self.buffer).await?;
+        if len == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.pos = 1;
+        self.len = len;
+        Ok(self.buffer[0])
+    }
+
+    /// Read a byte part way through a frame, respecting the timeout.
+    async fn read_frame_byte(&mut self) -> Result<u8> {
+        // Only start the timer if we actually have to wait.
+        if let Some(byte) = self.buffered_byte() {
+            return Ok(byte);
+        }
+
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.read_byte())
+                .await
+                .map_err(|_| SlipError::Timeout)?,
+            None => self.read_byte().await,
+        }
     }
 
-    fn check_and_remove_crc(&self, frame: Vec<u8>) -> Result<Vec<u8>> {
+    fn check_and_remove_crc(&self, frame: &mut Vec<u8>) -> Result<()> {
+        // A stray END (e.g. when the adapter resets) can give us a frame too short to contain
+        // the CRC16.
+        if frame.len() < 2 {
+            return Err(SlipError::MissingCrc.into());
+        }
         let len = frame.len() - 2;
 
         // Check CRC16 matches:
-        let bytes = (&frame[len..])
-            .try_into()
-            .map_err(|_| SlipError::MissingCrc)?;
-        let provided_crc = u16::from_le_bytes(bytes);
-        let calculated_crc = crc16(&frame[..len]);
+        let provided_crc = u16::from_le_bytes([frame[len], frame[len + 1]]);
+        let calculated_crc = self.checksum.calculate(&frame[..len]);
 
         if provided_crc != calculated_crc {
             return Err(SlipError::MismatchedCrc.into());
         }
 
         // Remove CRC16 bytes from returned frame:
-        let mut frame = frame;
         frame.truncate(len);
 
-        Ok(frame)
+        Ok(())
     }
 
+    #[cfg(any(test, feature = "testing"))]
     pub async fn read_frame(&mut self) -> Result<Vec<u8>> {
         let mut frame = Vec::new();
+        self.read_frame_into(&mut frame).await?;
+        Ok(frame)
+    }
+
+    /// Like `read_frame`, but reads into `frame`, which is cleared first. Reusing the same `Vec`
+    /// for each frame avoids allocating.
+    pub async fn read_frame_into(&mut self, frame: &mut Vec<u8>) -> Result<()> {
+        frame.clear();
         loop {
-            let mut byte = self.read_byte().await?;
+            let mut byte = if frame.is_empty() {
+                self.read_byte().await?
+            } else {
+                self.read_frame_byte().await?
+            };
 
             if byte == END {
                 // Skip END bytes at start of frame.
@@ -84,12 +186,15 @@ where
                     continue;
                 }
 
-                let frame = self.check_and_remove_crc(frame)?;
-                return Ok(frame);
+                self.check_and_remove_crc(frame)?;
+                if frame.len() < HEADER_LEN {
+                    return Err(SlipError::ShortFrame.into());
+                }
+                return Ok(());
             }
 
             if byte == ESC {
-                byte = match self.read_byte().await? {
+                byte = match self.read_frame_byte().await? {
                     ESC_ESC => ESC,
                     ESC_END => END,
                     _ => return Err(SlipError::InvalidEscape.into()),
@@ -106,55 +211,222 @@ where
     W: AsyncWrite + Unpin,
 {
     inner: BufWriter<W>,
+    checksum: Checksum,
 }
 
 impl<W> Writer<W>
 where
     W: AsyncWrite + Unpin,
 {
-    pub fn new(write: W) -> Self {
+    pub fn new(write: W, checksum: Checksum) -> Self {
         Self {
             inner: BufWriter::new(write),
+            checksum,
         }
     }
 
-    async fn write_byte(&mut self, byte: u8) -> Result<()> {
-        Ok(self.inner.write_u8(byte).await?)
-    }
-
-    async fn write_crc(&mut self, data: &[u8]) -> Result<()> {
-        let crc = crc16(data);
-        self.inner.write_u16_le(crc).await?;
-        Ok(())
-    }
-
+    /// Encodes the whole frame before writing it, so that it is written in one go rather than a
+    /// byte at a time.
     pub async fn write_frame(&mut self, data: &[u8]) -> Result<()> {
-        self.write_byte(END).await?;
-        for byte in data {
+        let crc = self.checksum.calculate(data).to_le_bytes();
+
+        // Allow for a few bytes needing to be escaped.
+        let mut frame = Vec::with_capacity(data.len() + crc.len() + 8);
+        frame.push(END);
+        // The CRC is escaped like the rest of the frame, as the reader unescapes it.
+        for byte in data.iter().chain(&crc) {
             match *byte {
-                ESC => {
-                    self.write_byte(ESC).await?;
-                    self.write_byte(ESC_ESC).await?;
-                }
-                END => {
-                    self.write_byte(ESC).await?;
-                    self.write_byte(ESC_END).await?;
-                }
-                byte => self.write_byte(byte).await?,
-            };
+                ESC => frame.extend(&[ESC, ESC_ESC]),
+                END => frame.extend(&[ESC, ESC_END]),
+                byte => frame.push(byte),
+            }
         }
-        self.write_crc(data).await?;
-        self.write_byte(END).await?;
+        frame.push(END);
+
+        self.inner.write_all(&frame).await?;
         self.inner.flush().await?;
         Ok(())
     }
 }
 
+fn sum16(data: &[u8]) -> u16 {
+    let mut sum: u16 = 0;
+    for byte in data {
+        sum = sum.wrapping_add(u16::from(*byte));
+    }
+    (!sum).wrapping_add(1)
+}
+
 fn crc16(data: &[u8]) -> u16 {
-    let mut crc = 0;
+    let mut crc: u16 = 0xFFFF;
     for byte in data {
-        crc += u16::from(*byte);
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
     }
-    crc = !crc + 1;
     crc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+    use crate::{Error, ErrorKind};
+
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    /// Frames with their checksums, as sent to the adapter.
+    const SUMMED_FRAMES: [&[u8]; 3] = [
+        // Version
+        &[
+            0x0D, 0x01, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE9, 0xFF,
+        ],
+        // DeviceState
+        &[0x07, 0x02, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0xEF, 0xFF],
+        // ReadParameter MacAddress
+        &[0x0A, 0x03, 0x00, 0x08, 0x00, 0x01, 0x00, 0x01, 0xE9, 0xFF],
+    ];
+
+    fn slip(frame: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![END];
+        bytes.extend(frame);
+        bytes.push(END);
+        bytes
+    }
+
+    #[test]
+    fn checksums() {
+        for frame in &SUMMED_FRAMES {
+            let (data, checksum) = frame.split_at(frame.len() - 2);
+            assert_eq!(Checksum::Sum.calculate(data).to_le_bytes(), checksum);
+        }
+
+        // The standard check value for CRC-16/CCITT-FALSE.
+        assert_eq!(Checksum::Crc16.calculate(b"123456789"), 0x29B1);
+    }
+
+    #[tokio::test]
+    async fn reads_summed_frames() {
+        for frame in &SUMMED_FRAMES {
+            let bytes = slip(frame);
+            let mut reader = Reader::new(&bytes[..], Checksum::Sum);
+            assert_eq!(
+                reader.read_frame().await.unwrap(),
+                &frame[..frame.len() - 2]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_checksum() {
+        let mut frame = SUMMED_FRAMES[0].to_vec();
+        *frame.last_mut().unwrap() ^= 0x01;
+        let bytes = slip(&frame);
+
+        let mut reader = Reader::new(&bytes[..], Checksum::Sum);
+        match reader.read_frame().await {
+            Err(Error {
+                kind: ErrorKind::Slip(SlipError::MismatchedCrc),
+            }) => {}
+            result => panic!("expected MismatchedCrc, got {:?}", result),
+        }
+
+        // The summed frame doesn't pass as CRC-16.
+        let bytes = slip(SUMMED_FRAMES[0]);
+        let mut reader = Reader::new(&bytes[..], Checksum::Crc16);
+        match reader.read_frame().await {
+            Err(Error {
+                kind: ErrorKind::Slip(SlipError::MismatchedCrc),
+            }) => {}
+            result => panic!("expected MismatchedCrc, got {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn short_frame_is_missing_crc() {
+        // A stray byte between ENDs is too short to hold a checksum, and mustn't panic.
+        let mut bytes = vec![END, 0x01, END];
+        bytes.extend(slip(SUMMED_FRAMES[1]));
+
+        let mut reader = Reader::new(&bytes[..], Checksum::Sum);
+        match reader.read_frame().await {
+            Err(Error {
+                kind: ErrorKind::Slip(SlipError::MissingCrc),
+            }) => {}
+            result => panic!("expected MissingCrc, got {:?}", result),
+        }
+
+        // The next frame is read as normal.
+        let frame = SUMMED_FRAMES[1];
+        assert_eq!(
+            reader.read_frame().await.unwrap(),
+            &frame[..frame.len() - 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn frame_shorter_than_header() {
+        // A single byte with a valid checksum, which mustn't reach code that reads the header.
+        let mut bytes = slip(&[0x01, 0xFF, 0xFF]);
+        bytes.extend(slip(SUMMED_FRAMES[1]));
+
+        let mut reader = Reader::new(&bytes[..], Checksum::Sum);
+        match reader.read_frame().await {
+            Err(Error {
+                kind: ErrorKind::Slip(SlipError::ShortFrame),
+            }) => {}
+            result => panic!("expected ShortFrame, got {:?}", result),
+        }
+
+        let frame = SUMMED_FRAMES[1];
+        assert_eq!(
+            reader.read_frame().await.unwrap(),
+            &frame[..frame.len() - 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn stalled_frame_times_out() {
+        let (mut port, adapter) = testing::duplex();
+        let mut reader = Reader::new_with_timeout(adapter, Checksum::Sum, TIMEOUT);
+
+        // The adapter stops part way through a frame.
+        let bytes = slip(SUMMED_FRAMES[1]);
+        port.write_all(&bytes[..5]).await.unwrap();
+        match reader.read_frame().await {
+            Err(Error {
+                kind: ErrorKind::Slip(SlipError::Timeout),
+            }) => {}
+            result => panic!("expected Timeout, got {:?}", result),
+        }
+
+        // The next frame is read as normal.
+        port.write_all(&bytes).await.unwrap();
+        let frame = SUMMED_FRAMES[1];
+        assert_eq!(
+            reader.read_frame().await.unwrap(),
+            &frame[..frame.len() - 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn idle_line_does_not_time_out() {
+        let (mut port, adapter) = testing::duplex();
+        let mut reader = Reader::new_with_timeout(adapter, Checksum::Sum, TIMEOUT);
+
+        // Nothing is sent for several timeouts before the frame starts.
+        let send = async {
+            tokio::time::delay_for(TIMEOUT * 3).await;
+            port.write_all(&slip(SUMMED_FRAMES[1])).await.unwrap();
+        };
+        let (result, ()) = tokio::join!(reader.read_frame(), send);
+
+        let frame = SUMMED_FRAMES[1];
+        assert_eq!(result.unwrap(), &frame[..frame.len() - 2]);
+    }
+}
@@ -0,0 +1,273 @@
+//! Helpers for exercising `Deconz` without an adapter.
+//!
+//! `MockAdapter::connect` returns a `Deconz` talking to an in-memory adapter. Script the adapter
+//! with `MockAdapter::respond` before making requests, and use `MockAdapter::send` to deliver
+//! unsolicited frames such as `Response::DeviceStateChanged`.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+
+use crate::slip;
+use crate::{
+    ApsReader, Checksum, CommandId, Deconz, DeconzConfig, ErrorKind, Response, Result, SequenceId,
+};
+
+/// The bytes written to one end of a `DuplexStream` that are yet to be read from the other.
+#[derive(Default)]
+struct Pipe {
+    buffer: VecDeque<u8>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// One end of an in-memory, bidirectional byte stream. Created with `duplex`.
+///
+/// Writes never block. Reads reach EOF once the other end has been shut down or dropped.
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+/// Creates a pair of connected streams: bytes written to one can be read from the other.
+pub fn duplex() -> (DuplexStream, DuplexStream) {
+    let a = Arc::new(Mutex::new(Pipe::default()));
+    let b = Arc::new(Mutex::new(Pipe::default()));
+
+    let left = DuplexStream {
+        read: a.clone(),
+        write: b.clone(),
+    };
+    let right = DuplexStream { read: b, write: a };
+
+    (left, right)
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.read.lock().expect("poisoned");
+
+        if pipe.buffer.is_empty() {
+            if pipe.closed {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(pipe.buffer.len());
+        for (byte, buffered) in buf.iter_mut().zip(pipe.buffer.drain(..len)) {
+            *byte = buffered;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.write.lock().expect("poisoned");
+
+        if pipe.closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        pipe.buffer.extend(buf);
+        if let Some(waker) = pipe.waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write.lock().expect("poisoned").close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        // The other end sees EOF when reading, and an error when writing.
+        self.write.lock().expect("poisoned").close();
+        self.read.lock().expect("poisoned").close();
+    }
+}
+
+/// Responses waiting to be sent, keyed by the command ID of the request they answer.
+type Script = Arc<Mutex<HashMap<u8, VecDeque<Response>>>>;
+
+/// A scriptable stand-in for the adapter, which answers each request with the next response
+/// queued for its command by `respond`. Requests with no queued response are left unanswered.
+pub struct MockAdapter {
+    script: Script,
+    frames: mpsc::Sender<(SequenceId, Response)>,
+}
+
+impl MockAdapter {
+    /// Spawns an adapter talking over `transport`, which would typically be one end of `duplex`.
+    pub fn new(transport: DuplexStream) -> Self {
+        let (reader, writer) = tokio::io::split(transport);
+        let script = Script::default();
+        let (frames_tx, frames) = mpsc::channel(1);
+
+        let rx = Rx {
+            reader: slip::Reader::new(reader, Checksum::default()),
+            script: script.clone(),
+            frames: frames_tx.clone(),
+        };
+        let tx = Tx {
+            writer: slip::Writer::new(writer, Checksum::default()),
+            frames,
+        };
+
+        tokio::spawn(rx.task());
+        tokio::spawn(tx.task());
+
+        Self {
+            script,
+            frames: frames_tx,
+        }
+    }
+
+    /// Spawns an adapter and connects a `Deconz` to it.
+    pub fn connect() -> (Deconz, ApsReader, Self) {
+        Self::connect_with_config(DeconzConfig::default())
+    }
+
+    /// Like `connect`, but with a non-default configuration. See `Deconz::with_config`.
+    pub fn connect_with_config(config: DeconzConfig) -> (Deconz, ApsReader, Self) {
+        let (left, right) = duplex();
+        let (reader, writer) = tokio::io::split(left);
+        let (deconz, aps_reader) = Deconz::with_config(reader, writer, config);
+        (deconz, aps_reader, Self::new(right))
+    }
+
+    /// Queue `response` to be sent in reply to the next request with the same command ID.
+    pub fn respond(&self, response: Response) {
+        let command_id = u8::from(response.command_id());
+        self.script
+            .lock()
+            .expect("poisoned")
+            .entry(command_id)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Send a frame that wasn't solicited by a request, such as `Response::DeviceStateChanged`.
+    pub async fn send(&self, response: Response) -> Result<()> {
+        self.frames
+            .clone()
+            .send((0, response))
+            .await
+            .map_err(|_| ErrorKind::ChannelError)?;
+        Ok(())
+    }
+}
+
+/// Task responsible for reading requests and picking the scripted response to each.
+struct Rx<R>
+where
+    R: AsyncRead + Unpin,
+{
+    reader: slip::Reader<R>,
+    script: Script,
+    frames: mpsc::Sender<(SequenceId, Response)>,
+}
+
+impl<R> Rx<R>
+where
+    R: AsyncRead + Unpin,
+{
+    async fn task(mut self) -> Result<()> {
+        loop {
+            let frame = match self.reader.read_frame().await {
+                Ok(frame) => frame,
+                Err(error) if error.is_eof() => break,
+                Err(error) => {
+                    error!("mock adapter rx: {}", error);
+                    continue;
+                }
+            };
+
+            let (command_id, sequence_id) = match (frame.first(), frame.get(1)) {
+                (Some(&command_id), Some(&sequence_id)) => (command_id, sequence_id),
+                _ => {
+                    error!("mock adapter rx: short frame: {:?}", frame);
+                    continue;
+                }
+            };
+
+            let response = self
+                .script
+                .lock()
+                .expect("poisoned")
+                .get_mut(&command_id)
+                .and_then(|responses| responses.pop_front());
+            let response = match response {
+                Some(response) => response,
+                None => {
+                    match CommandId::try_from(command_id) {
+                        Ok(command_id) => warn!("mock adapter rx: no response for {}", command_id),
+                        Err(error) => warn!("mock adapter rx: {}", error),
+                    }
+                    continue;
+                }
+            };
+
+            if self.frames.send((sequence_id, response)).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Task responsible for writing responses, solicited or not.
+struct Tx<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer: slip::Writer<W>,
+    frames: mpsc::Receiver<(SequenceId, Response)>,
+}
+
+impl<W> Tx<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    async fn task(mut self) -> Result<()> {
+        while let Some((sequence_id, response)) = self.frames.recv().await {
+            let frame = response.into_frame(sequence_id)?;
+            self.writer.write_frame(&frame).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,11 +1,16 @@
 use std::fmt::{self, Debug};
 use std::io::{Read, Write};
+use std::str::FromStr;
 
-use crate::{Error, ReadWire, ReadWireExt, Result, WriteWire};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::protocol;
+use crate::{Error, ErrorKind, ReadWire, ReadWireExt, Result, WriteWire};
 
 pub type SequenceId = u8;
 
-#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Endpoint(pub u8);
 
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
@@ -14,14 +19,32 @@ pub struct ProfileId(pub u16);
 #[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd)]
 pub struct ClusterId(pub u16);
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct ShortAddress(pub u16);
 
-#[derive(Clone, Copy, Eq, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct ExtendedAddress(pub u64);
 
 macro_rules! wrapped_primitive {
-    ($ident:ident, $repr:expr) => {
+    ($ident:ident, $primitive:ty, $accessor:ident, $repr:expr) => {
+        impl $ident {
+            pub fn $accessor(self) -> $primitive {
+                self.0
+            }
+        }
+
+        impl From<$primitive> for $ident {
+            fn from(value: $primitive) -> Self {
+                $ident(value)
+            }
+        }
+
+        impl From<$ident> for $primitive {
+            fn from(value: $ident) -> Self {
+                value.0
+            }
+        }
+
         impl ReadWire for $ident {
             type Error = Error;
 
@@ -54,16 +77,156 @@ macro_rules! wrapped_primitive {
                 write!(f, $repr, self.0)
             }
         }
+
+        impl fmt::Display for $ident {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, $repr, self.0)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl Serialize for $ident {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        /// Accepts hex strings, with or without the `0x` prefix.
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $ident {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let s = String::deserialize(deserializer)?;
+                let digits = s.strip_prefix("0x").unwrap_or(&s);
+                <$primitive>::from_str_radix(digits, 16)
+                    .map($ident)
+                    .map_err(|_| {
+                        serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(&s),
+                            &"a hexadecimal string",
+                        )
+                    })
+            }
+        }
     };
 }
 
-wrapped_primitive!(Endpoint, "{:#04x}");
-wrapped_primitive!(ProfileId, "{:#06x}");
-wrapped_primitive!(ClusterId, "{:#06x}");
-wrapped_primitive!(ShortAddress, "{:#06x}");
-wrapped_primitive!(ExtendedAddress, "{:#010x}");
+wrapped_primitive!(Endpoint, u8, as_u8, "{:#04x}");
+wrapped_primitive!(ProfileId, u16, as_u16, "{:#06x}");
+wrapped_primitive!(ClusterId, u16, as_u16, "{:#06x}");
+wrapped_primitive!(ShortAddress, u16, as_u16, "{:#06x}");
+wrapped_primitive!(ExtendedAddress, u64, as_u64, "{:#018x}");
+
+impl ClusterId {
+    // ZCL clusters.
+    pub const BASIC: ClusterId = ClusterId(0x0000);
+    pub const POWER_CONFIGURATION: ClusterId = ClusterId(0x0001);
+    pub const IDENTIFY: ClusterId = ClusterId(0x0003);
+    pub const GROUPS: ClusterId = ClusterId(0x0004);
+    pub const SCENES: ClusterId = ClusterId(0x0005);
+    pub const ON_OFF: ClusterId = ClusterId(0x0006);
+    pub const LEVEL_CONTROL: ClusterId = ClusterId(0x0008);
+    pub const OTA_UPGRADE: ClusterId = ClusterId(0x0019);
+    pub const GREEN_POWER: ClusterId = ClusterId(0x0021);
+    pub const COLOR_CONTROL: ClusterId = ClusterId(0x0300);
+    pub const ILLUMINANCE_MEASUREMENT: ClusterId = ClusterId(0x0400);
+    pub const TEMPERATURE_MEASUREMENT: ClusterId = ClusterId(0x0402);
+    pub const OCCUPANCY_SENSING: ClusterId = ClusterId(0x0406);
+
+    // ZDO clusters.
+    pub const ZDO_NWK_ADDR_REQ: ClusterId = ClusterId(0x0000);
+    pub const ZDO_NWK_ADDR_RSP: ClusterId = zdo_response_cluster(Self::ZDO_NWK_ADDR_REQ);
+    pub const ZDO_IEEE_ADDR_REQ: ClusterId = ClusterId(0x0001);
+    pub const ZDO_IEEE_ADDR_RSP: ClusterId = zdo_response_cluster(Self::ZDO_IEEE_ADDR_REQ);
+    pub const ZDO_NODE_DESC_REQ: ClusterId = ClusterId(0x0002);
+    pub const ZDO_NODE_DESC_RSP: ClusterId = zdo_response_cluster(Self::ZDO_NODE_DESC_REQ);
+    pub const ZDO_POWER_DESC_REQ: ClusterId = ClusterId(0x0003);
+    pub const ZDO_POWER_DESC_RSP: ClusterId = zdo_response_cluster(Self::ZDO_POWER_DESC_REQ);
+    pub const ZDO_SIMPLE_DESC_REQ: ClusterId = ClusterId(0x0004);
+    pub const ZDO_SIMPLE_DESC_RSP: ClusterId = zdo_response_cluster(Self::ZDO_SIMPLE_DESC_REQ);
+    pub const ZDO_ACTIVE_EP_REQ: ClusterId = ClusterId(0x0005);
+    pub const ZDO_ACTIVE_EP_RSP: ClusterId = zdo_response_cluster(Self::ZDO_ACTIVE_EP_REQ);
+    pub const ZDO_MATCH_DESC_REQ: ClusterId = ClusterId(0x0006);
+    pub const ZDO_MATCH_DESC_RSP: ClusterId = zdo_response_cluster(Self::ZDO_MATCH_DESC_REQ);
+    /// Broadcast by a device when it joins or rejoins the network. It has no response.
+    pub const ZDO_DEVICE_ANNCE: ClusterId = ClusterId(0x0013);
+    pub const ZDO_BIND_REQ: ClusterId = ClusterId(0x0021);
+    pub const ZDO_BIND_RSP: ClusterId = zdo_response_cluster(Self::ZDO_BIND_REQ);
+    pub const ZDO_UNBIND_REQ: ClusterId = ClusterId(0x0022);
+    pub const ZDO_UNBIND_RSP: ClusterId = zdo_response_cluster(Self::ZDO_UNBIND_REQ);
+    pub const ZDO_MGMT_LQI_REQ: ClusterId = ClusterId(0x0031);
+    pub const ZDO_MGMT_LQI_RSP: ClusterId = zdo_response_cluster(Self::ZDO_MGMT_LQI_REQ);
+    pub const ZDO_MGMT_RTG_REQ: ClusterId = ClusterId(0x0032);
+    pub const ZDO_MGMT_RTG_RSP: ClusterId = zdo_response_cluster(Self::ZDO_MGMT_RTG_REQ);
+    pub const ZDO_MGMT_LEAVE_REQ: ClusterId = ClusterId(0x0034);
+    pub const ZDO_MGMT_LEAVE_RSP: ClusterId = zdo_response_cluster(Self::ZDO_MGMT_LEAVE_REQ);
+    pub const ZDO_MGMT_PERMIT_JOIN_REQ: ClusterId = ClusterId(0x0036);
+    pub const ZDO_MGMT_PERMIT_JOIN_RSP: ClusterId =
+        zdo_response_cluster(Self::ZDO_MGMT_PERMIT_JOIN_REQ);
+}
+
+/// The cluster ID of the response to a ZDO request: the request's cluster ID, with the high bit
+/// set.
+pub const fn zdo_response_cluster(request: ClusterId) -> ClusterId {
+    ClusterId(request.0 | 0x8000)
+}
+
+impl ProfileId {
+    /// The Zigbee Device Profile, used by the ZDO on endpoint 0.
+    pub const ZDP: ProfileId = ProfileId(0x0000);
+    /// Home Automation.
+    pub const HA: ProfileId = ProfileId(0x0104);
+    /// Zigbee Light Link.
+    pub const ZLL: ProfileId = ProfileId(0xC05E);
+    /// Zigbee Green Power.
+    pub const GREEN_POWER: ProfileId = ProfileId(0xA1E0);
+}
+
+impl ExtendedAddress {
+    /// Formats the address as colon-separated octets, most significant first (e.g.
+    /// `00:21:2e:ff:ff:01:23:45`), as shown by the deCONZ GUI and printed on device labels.
+    pub fn to_eui64_string(self) -> String {
+        let octets: Vec<String> = self
+            .0
+            .to_be_bytes()
+            .iter()
+            .map(|octet| format!("{:02x}", octet))
+            .collect();
+        octets.join(":")
+    }
+}
+
+impl FromStr for ExtendedAddress {
+    type Err = Error;
+
+    /// Parses addresses in the format produced by `to_eui64_string`, in either case.
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::from(ErrorKind::InvalidExtendedAddress(s.to_owned()));
 
-#[derive(Copy, Clone, Debug)]
+        let mut bytes = [0; 8];
+        let mut octets = s.split(':');
+        for byte in bytes.iter_mut() {
+            let octet = octets.next().ok_or_else(invalid)?;
+            if octet.len() != 2 || !octet.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            *byte = u8::from_str_radix(octet, 16).map_err(|_| invalid())?;
+        }
+        if octets.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(ExtendedAddress(u64::from_be_bytes(bytes)))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Platform {
     Avr,
     Arm,
@@ -71,12 +234,36 @@ pub enum Platform {
 }
 
 #[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Version {
     pub major: u8,
     pub minor: u8,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SecurityMode {
+    NoSecurity,
+    PreconfiguredNetworkKey,
+    NetworkKeyFromTrustCenter,
+    /// No master key, but a trust center link key.
+    NoMasterButTrustCenterLinkKey,
+}
+
+/// The 128-bit key used to secure the network. Not serializable, and redacted when formatted with
+/// `Debug`, so that it isn't written out by accident along with the other types (e.g. when
+/// requests are logged).
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct NetworkKey(pub [u8; 16]);
+
+impl Debug for NetworkKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NetworkKey(<redacted>)")
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum NetworkState {
     Offline,
     Joining,
@@ -84,7 +271,30 @@ pub enum NetworkState {
     Leaving,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl NetworkState {
+    /// Whether the adapter is on a network and able to send and receive requests.
+    pub fn is_operational(&self) -> bool {
+        *self == NetworkState::Connected
+    }
+}
+
+/// A change to the `NetworkState` reported by the adapter. See `Deconz::network_state_changes`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NetworkStateChange {
+    pub from: NetworkState,
+    pub to: NetworkState,
+}
+
+impl NetworkStateChange {
+    /// Whether the adapter has finished leaving the network, having been told to leave.
+    pub fn is_leave_completed(&self) -> bool {
+        self.from == NetworkState::Leaving && self.to == NetworkState::Offline
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DeviceState {
     pub network_state: NetworkState,
     pub data_confirm: bool,
@@ -105,13 +315,16 @@ impl Default for DeviceState {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DestinationAddress {
     Group(ShortAddress),
     Nwk(ShortAddress),
     Ieee(ExtendedAddress),
 }
 
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct SourceAddress {
     pub short: ShortAddress,
     pub extended: ExtendedAddress,
@@ -123,7 +336,8 @@ impl Debug for SourceAddress {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ApsDataIndication {
     pub destination_address: DestinationAddress,
     pub destination_endpoint: Endpoint,
@@ -134,25 +348,387 @@ pub struct ApsDataIndication {
     pub asdu: Vec<u8>,
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Well-known short addresses used to broadcast to many devices on the network.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BroadcastAddress {
+    /// 0xFFFF: every device, including sleepy end devices.
+    All,
+    /// 0xFFFD: every device with rx-on-when-idle.
+    RxOnWhenIdle,
+    /// 0xFFFC: the coordinator and all routers.
+    Routers,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Destination {
     Group(ShortAddress),
     Nwk(ShortAddress, Endpoint),
     Ieee(ExtendedAddress, Endpoint),
+    /// The endpoint should be 0 for ZDO requests, or 0xFF for all application endpoints.
+    Broadcast(BroadcastAddress, Endpoint),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ApsDataRequest {
     pub destination: Destination,
     pub profile_id: ProfileId,
     pub cluster_id: ClusterId,
     pub source_endpoint: Endpoint,
     pub asdu: Vec<u8>,
+    pub tx_options: TxOptions,
+    /// The maximum number of hops, or 0 for unlimited.
+    pub radius: u8,
+}
+
+impl ApsDataRequest {
+    /// The longest ASDU that fits in a single frame, allowing for the NWK and APS headers and
+    /// security. Longer ASDUs must be sent with `TxOptions::FRAGMENTATION`.
+    pub const MAX_ASDU_LEN: usize = 82;
+
+    pub fn builder() -> ApsDataRequestBuilder {
+        ApsDataRequestBuilder::default()
+    }
+
+    /// The longest ASDU that this request can carry: `MAX_ASDU_LEN`, unless fragmentation is
+    /// enabled, in which case it's only limited by the length fields of the frame.
+    pub fn max_asdu_len(&self) -> usize {
+        if self.tx_options.contains(TxOptions::FRAGMENTATION) {
+            protocol::max_frame_asdu_len(&self.destination)
+        } else {
+            Self::MAX_ASDU_LEN
+        }
+    }
+
+    /// Fails if the ASDU is too long to send, rather than letting the adapter reject it. If given,
+    /// `max_aps_payload` is the longest ASDU that the adapter's firmware can send.
+    pub(crate) fn check_asdu_len(&self, max_aps_payload: Option<usize>) -> Result<()> {
+        let max = match max_aps_payload {
+            Some(max_aps_payload) => self.max_asdu_len().min(max_aps_payload),
+            None => self.max_asdu_len(),
+        };
+        if self.asdu.len() > max {
+            return Err(ErrorKind::AsduTooLarge {
+                len: self.asdu.len(),
+                max,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Builds an `ApsDataRequest`. The destination and cluster must be set. Otherwise, the request is
+/// sent from endpoint 1 using the Home Automation profile, with the default `TxOptions` and an
+/// unlimited radius.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ApsDataRequestBuilder {
+    destination: Option<Destination>,
+    profile_id: ProfileId,
+    cluster_id: Option<ClusterId>,
+    source_endpoint: Endpoint,
+    asdu: Vec<u8>,
+    tx_options: TxOptions,
+    radius: u8,
+}
+
+impl Default for ApsDataRequestBuilder {
+    fn default() -> Self {
+        Self {
+            destination: None,
+            profile_id: ProfileId::HA,
+            cluster_id: None,
+            source_endpoint: Endpoint(1),
+            asdu: Vec::new(),
+            tx_options: TxOptions::default(),
+            radius: 0,
+        }
+    }
+}
+
+impl ApsDataRequestBuilder {
+    pub fn destination(mut self, destination: Destination) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn profile(mut self, profile_id: ProfileId) -> Self {
+        self.profile_id = profile_id;
+        self
+    }
+
+    pub fn cluster(mut self, cluster_id: ClusterId) -> Self {
+        self.cluster_id = Some(cluster_id);
+        self
+    }
+
+    pub fn source_endpoint(mut self, source_endpoint: Endpoint) -> Self {
+        self.source_endpoint = source_endpoint;
+        self
+    }
+
+    pub fn asdu(mut self, asdu: Vec<u8>) -> Self {
+        self.asdu = asdu;
+        self
+    }
+
+    pub fn tx_options(mut self, tx_options: TxOptions) -> Self {
+        self.tx_options = tx_options;
+        self
+    }
+
+    pub fn radius(mut self, radius: u8) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Fails if the destination or cluster haven't been set, or if the ASDU is too long to send
+    /// (see `ApsDataRequest::max_asdu_len`).
+    pub fn build(self) -> Result<ApsDataRequest> {
+        let destination = self
+            .destination
+            .ok_or(ErrorKind::MissingField("destination"))?;
+        let cluster_id = self.cluster_id.ok_or(ErrorKind::MissingField("cluster"))?;
+
+        let request = ApsDataRequest {
+            destination,
+            profile_id: self.profile_id,
+            cluster_id,
+            source_endpoint: self.source_endpoint,
+            asdu: self.asdu,
+            tx_options: self.tx_options,
+            radius: self.radius,
+        };
+        request.check_asdu_len(None)?;
+
+        Ok(request)
+    }
+}
+
+/// Transmission options for an ApsDataRequest, which can be combined with `|`. Defaults to
+/// `TxOptions::APS_ACK`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TxOptions(u8);
+
+impl TxOptions {
+    pub const SECURITY: TxOptions = TxOptions(0x01);
+    pub const USE_NWK_KEY: TxOptions = TxOptions(0x02);
+    pub const APS_ACK: TxOptions = TxOptions(0x04);
+    pub const FRAGMENTATION: TxOptions = TxOptions(0x08);
+
+    pub fn empty() -> Self {
+        TxOptions(0)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: TxOptions) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for TxOptions {
+    fn default() -> Self {
+        TxOptions::APS_ACK
+    }
+}
+
+impl std::ops::BitOr for TxOptions {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        TxOptions(self.0 | other.0)
+    }
+}
+
+impl Debug for TxOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TxOptions({:#04x})", self.0)
+    }
+}
+
+/// The outcome of an APS data request, as reported by the APS, NWK or MAC layer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ConfirmStatus {
+    Success,
+    /// APS: the destination didn't acknowledge the request.
+    NoAck,
+    /// NWK: route discovery to the destination failed.
+    NoRoute,
+    /// MAC: the request expired before a sleepy end device polled for it.
+    MacTransactionExpired,
+    /// MAC: the next hop didn't acknowledge the frame.
+    MacNoAck,
+    Unknown(u8),
+}
+
+impl ConfirmStatus {
+    /// Whether the failure may be transient, such that resending the request could succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ConfirmStatus::NoAck | ConfirmStatus::MacNoAck | ConfirmStatus::MacTransactionExpired
+        )
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ApsDataConfirm {
     pub destination: Destination,
     pub source_endpoint: Endpoint,
-    pub status: u8,
+    pub status: ConfirmStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parameter, Request};
+
+    const DESTINATIONS: [Destination; 3] = [
+        Destination::Group(ShortAddress(0x0001)),
+        Destination::Nwk(ShortAddress(0x1234), Endpoint(1)),
+        Destination::Ieee(ExtendedAddress(0x00212effff012345), Endpoint(1)),
+    ];
+
+    fn fragmented_request(destination: Destination, len: usize) -> Result<ApsDataRequest> {
+        ApsDataRequest::builder()
+            .destination(destination)
+            .cluster(ClusterId::OTA_UPGRADE)
+            .asdu(vec![0; len])
+            .tx_options(TxOptions::FRAGMENTATION)
+            .build()
+    }
+
+    fn round_trip<T>(value: T, expected: &[u8])
+    where
+        T: ReadWire<Error = Error> + WriteWire<Error = Error> + Copy + Debug + PartialEq,
+    {
+        let mut buffer = Vec::new();
+        value.write_wire(&mut buffer).unwrap();
+        assert_eq!(buffer, expected, "{:?}", value);
+        assert_eq!(usize::from(value.wire_len()), expected.len(), "{:?}", value);
+        assert_eq!(T::read_wire(&mut &buffer[..]).unwrap(), value);
+    }
+
+    #[test]
+    fn wrapped_primitives_round_trip() {
+        round_trip(Endpoint(0xF2), &[0xF2]);
+        round_trip(ProfileId::HA, &[0x04, 0x01]);
+        round_trip(ClusterId::COLOR_CONTROL, &[0x00, 0x03]);
+        round_trip(ShortAddress(0xFFFD), &[0xFD, 0xFF]);
+        round_trip(
+            ExtendedAddress(0x00212effff012345),
+            &[0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00],
+        );
+    }
+
+    #[test]
+    fn debug_shows_every_digit() {
+        assert_eq!(
+            format!("{:?}", ExtendedAddress(0x00212effff012345)),
+            "0x00212effff012345"
+        );
+        assert_eq!(format!("{:?}", ExtendedAddress(0x1)), "0x0000000000000001");
+        assert_eq!(format!("{:?}", ShortAddress(0x1)), "0x0001");
+        assert_eq!(format!("{:?}", Endpoint(0x1)), "0x01");
+    }
+
+    #[test]
+    fn network_key_is_redacted() {
+        let key = NetworkKey([0xA5; 16]);
+        assert_eq!(format!("{:?}", key), "NetworkKey(<redacted>)");
+
+        // Requests are logged with `Debug`.
+        let request = Request::WriteParameter {
+            parameter: Parameter::NetworkKey(key),
+        };
+        let debug = format!("{:?}", request);
+        assert!(!debug.contains("a5") && !debug.contains("165"), "{}", debug);
+    }
+
+    #[test]
+    fn tx_options() {
+        assert_eq!(TxOptions::default(), TxOptions::APS_ACK);
+        assert_eq!(TxOptions::empty().bits(), 0x00);
+
+        let options = TxOptions::APS_ACK | TxOptions::FRAGMENTATION;
+        assert_eq!(options.bits(), 0x0C);
+        assert!(options.contains(TxOptions::APS_ACK));
+        assert!(options.contains(TxOptions::FRAGMENTATION));
+        assert!(options.contains(TxOptions::APS_ACK | TxOptions::FRAGMENTATION));
+        assert!(!options.contains(TxOptions::SECURITY));
+        assert!(!options.contains(TxOptions::SECURITY | TxOptions::APS_ACK));
+        assert!(options.contains(TxOptions::empty()));
+    }
+
+    #[test]
+    fn longest_fragmented_asdu_fills_frame() {
+        for &destination in &DESTINATIONS {
+            let max = protocol::max_frame_asdu_len(&destination);
+            let request = fragmented_request(destination, max).unwrap();
+
+            let frame = Request::ApsDataRequest(0, request).into_frame(0).unwrap();
+            assert_eq!(frame.len(), usize::from(u16::MAX));
+            assert_eq!(&frame[3..5], &u16::MAX.to_le_bytes());
+
+            match fragmented_request(destination, max + 1) {
+                Err(Error {
+                    kind: ErrorKind::AsduTooLarge { len, max: limit },
+                }) => {
+                    assert_eq!(len, max + 1);
+                    assert_eq!(limit, max);
+                }
+                result => panic!("expected AsduTooLarge, got {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn eui64_string_round_trip() {
+        let address = ExtendedAddress(0x00212effff012345);
+        let string = address.to_eui64_string();
+        assert_eq!(string, "00:21:2e:ff:ff:01:23:45");
+        assert_eq!(string.parse::<ExtendedAddress>().unwrap(), address);
+        assert_eq!(
+            "00:21:2E:FF:FF:01:23:45"
+                .parse::<ExtendedAddress>()
+                .unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn invalid_eui64_strings() {
+        for s in &[
+            // Too few and too many octets.
+            "00:21:2e:ff:ff:01:23",
+            "00:21:2e:ff:ff:01:23:45:67",
+            // Non-hex and wrongly-sized octets.
+            "00:21:2e:ff:ff:01:23:4g",
+            "00:21:2e:ff:ff:01:23:+5",
+            "00:21:2e:ff:ff:01:23:456",
+            // Missing or different separators.
+            "00212effff012345",
+            "00:21:2e:ff:ff:01:2345",
+            "00-21-2e-ff-ff-01-23-45",
+            "",
+        ] {
+            match s.parse::<ExtendedAddress>() {
+                Err(Error {
+                    kind: ErrorKind::InvalidExtendedAddress(input),
+                }) => assert_eq!(&input, s),
+                result => panic!(
+                    "expected InvalidExtendedAddress for {:?}, got {:?}",
+                    s, result
+                ),
+            }
+        }
+    }
 }
@@ -3,6 +3,7 @@ use std::future::Future;
 use std::hash::Hash;
 use std::result::Result;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use futures_util::future::FutureExt;
 use tokio::sync::oneshot;
@@ -34,6 +35,23 @@ where
         self.map.lock().expect("posoined").remove(&id)
     }
 
+    /// Remove all registered senders, e.g. so that they can be notified of a failure.
+    pub fn deregister_all(&self) -> Vec<oneshot::Sender<Result<Success, Error>>> {
+        let mut map = self.map.lock().expect("poisoned");
+        map.drain().map(|(_, sender)| sender).collect()
+    }
+
+    /// Fail everything that is still awaiting a response, e.g. because no more responses will
+    /// arrive.
+    pub fn cancel_all<F>(&self, error: F)
+    where
+        F: Fn() -> Error,
+    {
+        for sender in self.deregister_all() {
+            let _ = sender.send(Err(error()));
+        }
+    }
+
     pub fn send(&self, id: &Id, result: Result<Success, Error>) -> Option<Result<Success, Error>> {
         match self.deregister(id) {
             Some(sender) => {
@@ -51,6 +69,9 @@ where
         }
     }
 
+    /// Registers `sender` and then drives `future`, which is expected to send the request. If the
+    /// future fails, the error is sent to `sender` so that whoever is awaiting the response sees
+    /// it immediately, rather than waiting until they time out.
     pub async fn register_while<F, R, E>(
         self,
         id: Id,
@@ -68,6 +89,44 @@ where
         });
         future.await;
     }
+
+    /// Like `register_while`, but sends `timeout_error` to `sender` and deregisters `id` if no
+    /// response has been sent within `timeout`, so that the map doesn't grow forever when a
+    /// response never arrives.
+    pub async fn register_while_timeout<F, R, E>(
+        self,
+        id: Id,
+        sender: oneshot::Sender<Result<Success, Error>>,
+        future: F,
+        timeout: Duration,
+        timeout_error: Error,
+    ) where
+        F: Future<Output = Result<R, E>>,
+        E: Into<Error>,
+    {
+        // Register our own channel, so that we know whether the response arrived in time.
+        let (inner_sender, inner_receiver) = oneshot::channel();
+        let awaiting = self.clone();
+        let inner_id = id.clone();
+        let wait = async move {
+            awaiting
+                .register_while(inner_id, inner_sender, future)
+                .await;
+            inner_receiver.await
+        };
+
+        match tokio::time::timeout(timeout, wait).await {
+            Ok(Ok(result)) => {
+                let _ = sender.send(result);
+            }
+            // Our sender was replaced or dropped, so dropping theirs is the best we can do.
+            Ok(Err(_)) => {}
+            Err(_) => {
+                self.deregister(&id);
+                let _ = sender.send(Err(timeout_error));
+            }
+        }
+    }
 }
 
 impl<Id, Success, Error> Clone for Awaiting<Id, Success, Error> {
@@ -77,3 +136,102 @@ impl<Id, Success, Error> Clone for Awaiting<Id, Success, Error> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestAwaiting = Awaiting<u8, u32, &'static str>;
+
+    const ID: u8 = 0x2A;
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    fn is_empty(awaiting: &TestAwaiting) -> bool {
+        awaiting.map.lock().expect("poisoned").is_empty()
+    }
+
+    async fn sent() -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn response_before_timeout() {
+        let awaiting = TestAwaiting::new();
+        let (sender, receiver) = oneshot::channel();
+
+        let request =
+            awaiting
+                .clone()
+                .register_while_timeout(ID, sender, sent(), TIMEOUT, "timeout");
+        let respond = async {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+            assert!(awaiting.send(&ID, Ok(5)).is_none());
+        };
+        tokio::join!(request, respond);
+
+        assert_eq!(receiver.await.unwrap(), Ok(5));
+        assert!(is_empty(&awaiting));
+    }
+
+    #[tokio::test]
+    async fn timeout_deregisters() {
+        let awaiting = TestAwaiting::new();
+        let (sender, receiver) = oneshot::channel();
+
+        awaiting
+            .clone()
+            .register_while_timeout(ID, sender, sent(), TIMEOUT, "timeout")
+            .await;
+
+        assert_eq!(receiver.await.unwrap(), Err("timeout"));
+        assert!(is_empty(&awaiting));
+    }
+
+    #[tokio::test]
+    async fn send_failure_before_timeout() {
+        let awaiting = TestAwaiting::new();
+        let (sender, receiver) = oneshot::channel();
+
+        let failed = async { Err::<(), _>("send failed") };
+        awaiting
+            .clone()
+            .register_while_timeout(ID, sender, failed, TIMEOUT, "timeout")
+            .await;
+
+        assert_eq!(receiver.await.unwrap(), Err("send failed"));
+        assert!(is_empty(&awaiting));
+    }
+
+    #[tokio::test]
+    async fn response_after_timeout_is_unsolicited() {
+        let awaiting = TestAwaiting::new();
+        let (sender, receiver) = oneshot::channel();
+
+        awaiting
+            .clone()
+            .register_while_timeout(ID, sender, sent(), TIMEOUT, "timeout")
+            .await;
+
+        assert_eq!(receiver.await.unwrap(), Err("timeout"));
+        assert_eq!(awaiting.send(&ID, Ok(5)), Some(Ok(5)));
+    }
+
+    #[tokio::test]
+    async fn cancel_all_fails_every_receiver() {
+        let awaiting = TestAwaiting::new();
+        let receivers: Vec<_> = (0..3)
+            .map(|id| {
+                let (sender, receiver) = oneshot::channel();
+                awaiting.register(id, sender);
+                receiver
+            })
+            .collect();
+
+        awaiting.cancel_all(|| "cancelled");
+
+        assert!(is_empty(&awaiting));
+        for receiver in receivers {
+            assert_eq!(receiver.await.unwrap(), Err("cancelled"));
+        }
+    }
+}
@@ -4,15 +4,54 @@ use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
 
 /// Atomic counter that generates u8 request IDs, wrapping on overflow.
-#[derive(Clone, Default)]
-pub struct IncrementingId(Arc<AtomicU8>);
+#[derive(Clone)]
+pub struct IncrementingId {
+    next: Arc<AtomicU8>,
+    step: u8,
+}
 
 impl IncrementingId {
     pub fn new() -> Self {
-        Default::default()
+        Self::with_step(0, 1)
+    }
+
+    /// Generates IDs starting from `start` and advancing by `step` each time.
+    pub fn with_step(start: u8, step: u8) -> Self {
+        Self {
+            next: Arc::new(AtomicU8::new(start)),
+            step,
+        }
     }
 
     pub fn next(&self) -> u8 {
-        self.0.fetch_add(1, Ordering::SeqCst)
+        self.next.fetch_add(self.step, Ordering::SeqCst)
+    }
+}
+
+impl Default for IncrementingId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incrementing_id_wraps() {
+        let ids = IncrementingId::with_step(250, 5);
+        assert_eq!(ids.next(), 250);
+        assert_eq!(ids.next(), 255);
+        assert_eq!(ids.next(), 4);
+    }
+
+    #[test]
+    fn incrementing_id_is_shared_between_clones() {
+        let ids = IncrementingId::new();
+        let clone = ids.clone();
+        assert_eq!(ids.next(), 0);
+        assert_eq!(clone.next(), 1);
+        assert_eq!(ids.next(), 2);
     }
 }
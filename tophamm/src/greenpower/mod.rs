@@ -0,0 +1,162 @@
+pub mod protocol;
+
+use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use deconz::{ApsDataIndication, ApsReader, ClusterId, ProfileId, ReadWireExt};
+use tokio::stream::Stream;
+
+use crate::zcl::{Result, ZclFrame};
+
+use self::protocol::Notification;
+
+pub use self::protocol::{GpdCommand, GpdId};
+
+/// A command from a Green Power Device, such as a button press on an energy-harvesting switch,
+/// as forwarded by a proxy. Frame counters and security aren't checked.
+#[derive(Debug)]
+pub struct GreenPowerEvent {
+    pub gpd_id: GpdId,
+    pub frame_counter: u32,
+    pub command: GpdCommand,
+    pub payload: Vec<u8>,
+    /// Whether the frame was forwarded while the proxy was in commissioning mode.
+    pub commissioning: bool,
+}
+
+/// Parses the indication as a GP Notification or GP Commissioning Notification. Returns `None`
+/// for other indications.
+pub fn parse_green_power(
+    aps_data_indication: &ApsDataIndication,
+) -> Result<Option<GreenPowerEvent>> {
+    if aps_data_indication.profile_id != ProfileId::GREEN_POWER
+        || aps_data_indication.cluster_id != ClusterId::GREEN_POWER
+    {
+        return Ok(None);
+    }
+
+    let frame: ZclFrame = Cursor::new(&aps_data_indication.asdu).read_wire()?;
+    if !frame.frame_control.is_cluster_specific() {
+        return Ok(None);
+    }
+    let commissioning = match frame.command_id {
+        Notification::COMMAND_ID => false,
+        Notification::COMMISSIONING_COMMAND_ID => true,
+        _ => return Ok(None),
+    };
+
+    let notification: Notification = Cursor::new(&frame.payload).read_wire()?;
+    Ok(Some(GreenPowerEvent {
+        gpd_id: notification.gpd_id,
+        frame_counter: notification.frame_counter,
+        command: GpdCommand::from(notification.command_id),
+        payload: notification.payload,
+        commissioning,
+    }))
+}
+
+pub trait GreenPowerExt {
+    /// Turns the reader into a stream of Green Power events. Other indications, and notifications
+    /// that can't be parsed, are discarded.
+    fn green_power(self) -> GreenPowerEvents;
+}
+
+impl GreenPowerExt for ApsReader {
+    fn green_power(self) -> GreenPowerEvents {
+        GreenPowerEvents { reader: self }
+    }
+}
+
+/// A stream of Green Power events. Created with `GreenPowerExt::green_power`.
+pub struct GreenPowerEvents {
+    reader: ApsReader,
+}
+
+impl Stream for GreenPowerEvents {
+    type Item = GreenPowerEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let aps_data_indication = match Pin::new(&mut self.reader).poll_next(cx) {
+                Poll::Ready(Some(aps_data_indication)) => aps_data_indication,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match parse_green_power(&aps_data_indication) {
+                Ok(Some(event)) => return Poll::Ready(Some(event)),
+                Ok(None) => {}
+                Err(error) => error!("green power: invalid notification: {}", error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deconz::{DestinationAddress, Endpoint, ExtendedAddress, ShortAddress, SourceAddress};
+
+    use super::*;
+
+    /// A GP Notification of a Toggle from source ID 0x01234567.
+    const NOTIFICATION: [u8; 15] = [
+        0x11, 0x05, 0x00, 0x00, 0x00, 0x67, 0x45, 0x23, 0x01, 0x2A, 0x00, 0x00, 0x00, 0x22, 0x00,
+    ];
+
+    fn indication(profile_id: ProfileId, cluster_id: ClusterId, asdu: &[u8]) -> ApsDataIndication {
+        ApsDataIndication {
+            destination_address: DestinationAddress::Group(ShortAddress(0x0B84)),
+            destination_endpoint: Endpoint(0xF2),
+            source_address: SourceAddress {
+                short: ShortAddress(0x1234),
+                extended: ExtendedAddress(0x00212effff012345),
+            },
+            source_endpoint: Endpoint(0xF2),
+            profile_id,
+            cluster_id,
+            asdu: asdu.to_vec(),
+        }
+    }
+
+    #[test]
+    fn notification() {
+        let ind = indication(
+            ProfileId::GREEN_POWER,
+            ClusterId::GREEN_POWER,
+            &NOTIFICATION,
+        );
+        let event = parse_green_power(&ind).unwrap().unwrap();
+        assert_eq!(event.gpd_id, GpdId::SourceId(0x01234567));
+        assert_eq!(event.frame_counter, 42);
+        assert_eq!(event.command, GpdCommand::Toggle);
+        assert!(!event.commissioning);
+    }
+
+    #[test]
+    fn commissioning_notification() {
+        let mut asdu = NOTIFICATION;
+        asdu[2] = Notification::COMMISSIONING_COMMAND_ID;
+        let ind = indication(ProfileId::GREEN_POWER, ClusterId::GREEN_POWER, &asdu);
+        let event = parse_green_power(&ind).unwrap().unwrap();
+        assert!(event.commissioning);
+    }
+
+    #[test]
+    fn other_profiles_and_clusters_are_ignored() {
+        let ind = indication(ProfileId::HA, ClusterId::GREEN_POWER, &NOTIFICATION);
+        assert!(parse_green_power(&ind).unwrap().is_none());
+
+        let ind = indication(ProfileId::GREEN_POWER, ClusterId::ON_OFF, &NOTIFICATION);
+        assert!(parse_green_power(&ind).unwrap().is_none());
+    }
+
+    #[test]
+    fn other_commands_are_ignored() {
+        // A global Read Attributes, and the cluster-specific GP Pairing command.
+        for asdu in &[&[0x10, 0x05, 0x00, 0x00, 0x00][..], &[0x19, 0x05, 0x01]] {
+            let ind = indication(ProfileId::GREEN_POWER, ClusterId::GREEN_POWER, asdu);
+            assert!(parse_green_power(&ind).unwrap().is_none());
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::io::Read;
+
+use deconz::{Endpoint, ExtendedAddress, ReadWire, ReadWireExt};
+
+use crate::zcl::{Error, ErrorKind, Result};
+
+/// The bits of the notification options holding the GPD's application ID.
+const OPTIONS_APPLICATION_ID: u16 = 0x0007;
+
+/// Identifies a Green Power Device, depending on its application ID.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GpdId {
+    /// Application ID 0: a 32-bit source ID.
+    SourceId(u32),
+    /// Application ID 2: an IEEE address and endpoint.
+    Ieee(ExtendedAddress, Endpoint),
+}
+
+/// A command sent by a Green Power Device. Commands without a variant are kept as `Other`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GpdCommand {
+    RecallScene(u8),
+    StoreScene(u8),
+    Off,
+    On,
+    Toggle,
+    Commissioning,
+    Decommissioning,
+    Other(u8),
+}
+
+impl From<u8> for GpdCommand {
+    fn from(command_id: u8) -> Self {
+        match command_id {
+            0x10..=0x17 => GpdCommand::RecallScene(command_id - 0x10),
+            0x18..=0x1F => GpdCommand::StoreScene(command_id - 0x18),
+            0x20 => GpdCommand::Off,
+            0x21 => GpdCommand::On,
+            0x22 => GpdCommand::Toggle,
+            0xE0 => GpdCommand::Commissioning,
+            0xE1 => GpdCommand::Decommissioning,
+            other => GpdCommand::Other(other),
+        }
+    }
+}
+
+/// The GP Notification and GP Commissioning Notification commands, which a proxy sends to forward
+/// a frame it received from a Green Power Device. Both start with the same fields; any that
+/// follow the payload, such as proxy information, are ignored.
+#[derive(Debug)]
+pub struct Notification {
+    pub options: u16,
+    pub gpd_id: GpdId,
+    pub frame_counter: u32,
+    pub command_id: u8,
+    pub payload: Vec<u8>,
+}
+
+impl Notification {
+    pub const COMMAND_ID: u8 = 0x00;
+    pub const COMMISSIONING_COMMAND_ID: u8 = 0x04;
+}
+
+impl ReadWire for Notification {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let options: u16 = r.read_wire()?;
+        let gpd_id = match (options & OPTIONS_APPLICATION_ID) as u8 {
+            0 => GpdId::SourceId(r.read_wire()?),
+            2 => GpdId::Ieee(r.read_wire()?, r.read_wire()?),
+            application_id => {
+                return Err(ErrorKind::UnsupportedGpdApplicationId(application_id).into())
+            }
+        };
+        let frame_counter = r.read_wire()?;
+        let command_id = r.read_wire()?;
+        let len: u8 = r.read_wire()?;
+        let mut payload = vec![0; usize::from(len)];
+        r.read_exact(&mut payload)?;
+
+        Ok(Notification {
+            options,
+            gpd_id,
+            frame_counter,
+            command_id,
+            payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_id_notification() {
+        // A Toggle from a switch with source ID 0x01234567, followed by the proxy's GPP short
+        // address (0x1234) and link quality.
+        let bytes = [
+            0x08, 0x00, 0x67, 0x45, 0x23, 0x01, 0x2A, 0x00, 0x00, 0x00, 0x22, 0x00, 0x34, 0x12,
+            0xC7,
+        ];
+        let notification = Notification::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(notification.options, 0x0008);
+        assert_eq!(notification.gpd_id, GpdId::SourceId(0x01234567));
+        assert_eq!(notification.frame_counter, 42);
+        assert_eq!(
+            GpdCommand::from(notification.command_id),
+            GpdCommand::Toggle
+        );
+        assert!(notification.payload.is_empty());
+    }
+
+    #[test]
+    fn ieee_notification() {
+        // Recall Scene 2 from endpoint 1 of an IEEE-addressed device, with a 1-byte payload.
+        let bytes = [
+            0x02, 0x00, 0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00, 0x01, 0x10, 0x27, 0x00,
+            0x00, 0x12, 0x01, 0x7F,
+        ];
+        let notification = Notification::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(
+            notification.gpd_id,
+            GpdId::Ieee(ExtendedAddress(0x00212effff012345), Endpoint(0x01))
+        );
+        assert_eq!(notification.frame_counter, 10000);
+        assert_eq!(
+            GpdCommand::from(notification.command_id),
+            GpdCommand::RecallScene(2)
+        );
+        assert_eq!(notification.payload, [0x7F]);
+    }
+
+    #[test]
+    fn unsupported_application_id() {
+        let bytes = [0x01, 0x00, 0x67, 0x45, 0x23, 0x01];
+        match Notification::read_wire(&mut &bytes[..]) {
+            Err(Error {
+                kind: ErrorKind::UnsupportedGpdApplicationId(1),
+            }) => {}
+            result => panic!("expected UnsupportedGpdApplicationId, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate log;
+
+pub mod greenpower;
+pub mod ota;
+pub mod zcl;
+pub mod zdo;
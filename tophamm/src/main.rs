@@ -1,13 +1,13 @@
 #[macro_use]
 extern crate log;
 
-mod zdo;
-
-use deconz::{Destination, Endpoint, ShortAddress};
+use deconz::{ClusterId, Endpoint, ProfileId};
 use tokio::stream::StreamExt;
-use tokio::sync::mpsc;
 
-use crate::zdo::{Result, Zdo};
+use tophamm::greenpower::GreenPowerExt;
+use tophamm::ota::{OtaImage, OtaServer};
+use tophamm::zcl::{self, ApsReaderExt, Zcl};
+use tophamm::zdo::{Result, Zdo};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -21,20 +21,53 @@ async fn main() -> Result<()> {
     // let fut1 = deconz.version();
     let fut2 = deconz.device_state();
 
-    let (zdo_tx, zdo_rx) = mpsc::channel(1);
-    let zdo = Zdo::new(deconz.clone(), zdo_rx);
+    let (zdo_reader, aps_reader) =
+        aps_reader.partition(|indication| indication.destination_endpoint == Endpoint(0));
+    let zdo = Zdo::new(deconz.clone(), zdo_reader);
+
+    let (zcl_reader, aps_reader) =
+        aps_reader.partition(|indication| indication.profile_id == ProfileId::HA);
+    let (reports_reader, zcl_reader) = zcl_reader.partition(zcl::is_attribute_report);
+
+    // Serve an OTA upgrade image, if one was given.
+    let (ota_reader, zcl_reader) =
+        zcl_reader.partition(|indication| indication.cluster_id == ClusterId::OTA_UPGRADE);
+    let images = match args.get(2) {
+        Some(image_path) => match OtaImage::parse(std::fs::read(image_path)?) {
+            Ok(image) => vec![image],
+            Err(error) => {
+                error!("{}: {}", image_path, error);
+                vec![]
+            }
+        },
+        None => vec![],
+    };
+    tokio::spawn(OtaServer::new(deconz.clone(), ota_reader, images).task());
+    let _zcl = Zcl::new(deconz.clone(), zcl_reader);
 
     tokio::spawn(async move {
+        let mut reports = reports_reader.zcl_reports();
+
+        while let Some(report) = reports.next().await {
+            info!("attribute report: {:?}", report);
+        }
+    });
+
+    let (green_power_reader, aps_reader) =
+        aps_reader.partition(|indication| indication.profile_id == ProfileId::GREEN_POWER);
+    tokio::spawn(async move {
+        let mut events = green_power_reader.green_power();
+
+        while let Some(event) = events.next().await {
+            info!("green power: {:?}", event);
+        }
+    });
+
+    let other_frames = tokio::spawn(async move {
         let mut aps_reader = aps_reader;
-        let mut zdo_tx = zdo_tx;
 
         while let Some(aps_data_indication) = aps_reader.next().await {
-            if aps_data_indication.destination_endpoint == Endpoint(0) {
-                debug!("zdo frame: {:?}", aps_data_indication);
-                zdo_tx.send(aps_data_indication).await.unwrap()
-            } else {
-                debug!("other frame: {:?}", aps_data_indication);
-            }
+            debug!("other frame: {:?}", aps_data_indication);
         }
     });
 
@@ -48,21 +81,33 @@ async fn main() -> Result<()> {
 
     dbg!(fut2.await?);
 
-    for neighbor in zdo
-        .get_neighbors(Destination::Nwk(ShortAddress(0x0), Endpoint(0)))
-        .await?
-    {
-        debug!("querying neighbor {:?}", neighbor.network_address);
-
-        let endpoints = zdo.query_endpoints(neighbor.network_address).await?;
+    let topology = zdo.discover_network().await?;
+    for node in topology.nodes {
         info!(
-            "neighbor = {:?}, endpoints = {:?}",
-            neighbor.network_address, endpoints
+            "node = {:?} ({:?}), {:?}, reachable = {}, endpoints = {:?}",
+            node.network_address,
+            node.extended_address,
+            node.device_type,
+            node.reachable,
+            node.endpoints
+        );
+    }
+    for link in topology.links {
+        debug!(
+            "link = {:?} -> {:?} ({:?}, LQI {})",
+            link.from, link.to, link.relationship, link.link_quality_index
         );
     }
 
     // dbg!(fut1.await?);
     // dbg!(fut3.await?);
 
-    loop {}
+    tokio::signal::ctrl_c().await?;
+    info!("shutting down");
+
+    // Stopping the tasks ends the ApsReader, once any frames already received have been logged.
+    deconz.shutdown();
+    let _ = other_frames.await;
+
+    Ok(())
 }
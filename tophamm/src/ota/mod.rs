@@ -0,0 +1,277 @@
+pub mod protocol;
+
+use std::io::Cursor;
+
+use deconz::*;
+use tokio::stream::StreamExt;
+
+use crate::zcl::protocol::FrameControl;
+use crate::zcl::{Error, Result, ZclFrame, ZclStatus};
+
+use self::protocol::{
+    ImageBlockRequest, ImageBlockResponse, QueryNextImageRequest, QueryNextImageResponse,
+    UpgradeEndRequest, UpgradeEndResponse,
+};
+
+pub use self::protocol::OtaImage;
+
+/// The most image data sent in each Image Block Response, so that the response fits in a single
+/// APS frame.
+const MAX_BLOCK_SIZE: u8 = 64;
+
+/// Serves OTA upgrade images to clients, which poll for new images and then download them a block
+/// at a time. Requests are read from an `ApsReader` that receives the OTA Upgrade cluster.
+pub struct OtaServer {
+    deconz: Deconz,
+    aps_data_indications: ApsReader,
+    images: Vec<OtaImage>,
+}
+
+impl OtaServer {
+    pub fn new(deconz: Deconz, aps_data_indications: ApsReader, images: Vec<OtaImage>) -> Self {
+        Self {
+            deconz,
+            aps_data_indications,
+            images,
+        }
+    }
+
+    pub async fn task(mut self) -> Result<()> {
+        while let Some(aps_data_indication) = self.aps_data_indications.next().await {
+            if aps_data_indication.cluster_id != ClusterId::OTA_UPGRADE {
+                error!("ota: unexpected cluster: {:?}", aps_data_indication);
+                continue;
+            }
+
+            if let Err(error) = self.handle(aps_data_indication).await {
+                error!("ota: {}", error);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle(&self, aps_data_indication: ApsDataIndication) -> Result<()> {
+        let frame: ZclFrame = Cursor::new(&aps_data_indication.asdu).read_wire()?;
+        if !frame.frame_control.is_cluster_specific() {
+            debug!("ota: ignoring global command: {:#04x}", frame.command_id);
+            return Ok(());
+        }
+
+        let mut r = Cursor::new(&frame.payload);
+        let (command_id, payload) = match frame.command_id {
+            QueryNextImageRequest::COMMAND_ID => {
+                let response = self.query_next_image(r.read_wire()?);
+                (QueryNextImageResponse::COMMAND_ID, to_payload(response)?)
+            }
+            ImageBlockRequest::COMMAND_ID => {
+                let response = self.image_block(r.read_wire()?);
+                (ImageBlockResponse::COMMAND_ID, to_payload(response)?)
+            }
+            UpgradeEndRequest::COMMAND_ID => match self.upgrade_end(r.read_wire()?) {
+                Some(response) => (UpgradeEndResponse::COMMAND_ID, to_payload(response)?),
+                None => return Ok(()),
+            },
+            command_id => {
+                debug!("ota: ignoring command: {:#04x}", command_id);
+                return Ok(());
+            }
+        };
+
+        let response = ZclFrame {
+            frame_control: FrameControl::CLUSTER_SPECIFIC
+                | FrameControl::SERVER_TO_CLIENT
+                | FrameControl::DISABLE_DEFAULT_RESPONSE,
+            manufacturer_code: None,
+            transaction_seq: frame.transaction_seq,
+            command_id,
+            payload,
+        };
+        self.reply(&aps_data_indication, response).await
+    }
+
+    /// Sends `frame` back to the endpoint that sent the indication.
+    async fn reply(&self, aps_data_indication: &ApsDataIndication, frame: ZclFrame) -> Result<()> {
+        let mut asdu = Vec::new();
+        asdu.write_wire(frame)?;
+
+        let destination = Destination::Nwk(
+            aps_data_indication.source_address.short,
+            aps_data_indication.source_endpoint,
+        );
+        let request = ApsDataRequest::builder()
+            .destination(destination)
+            .profile(aps_data_indication.profile_id)
+            .cluster(ClusterId::OTA_UPGRADE)
+            .source_endpoint(aps_data_indication.destination_endpoint)
+            .asdu(asdu)
+            .build()?;
+        self.deconz.aps_data_request(request).await?;
+        Ok(())
+    }
+
+    /// Offers the newest image for the client's kind of device, if it is newer than its current
+    /// firmware.
+    fn query_next_image(&self, request: QueryNextImageRequest) -> QueryNextImageResponse {
+        let image = self
+            .images
+            .iter()
+            .filter(|image| image.matches(request.manufacturer_code, request.image_type))
+            .filter(|image| image.file_version > request.current_file_version)
+            .max_by_key(|image| image.file_version);
+
+        match image {
+            Some(image) => {
+                info!("ota: offering {:?} in reply to {:?}", image, request);
+                QueryNextImageResponse::Available {
+                    manufacturer_code: image.manufacturer_code,
+                    image_type: image.image_type,
+                    file_version: image.file_version,
+                    image_size: image.size(),
+                }
+            }
+            None => QueryNextImageResponse::NoImageAvailable,
+        }
+    }
+
+    fn image_block(&self, request: ImageBlockRequest) -> ImageBlockResponse {
+        let image = self.images.iter().find(|image| {
+            image.matches(request.manufacturer_code, request.image_type)
+                && image.file_version == request.file_version
+        });
+        let image = match image {
+            Some(image) => image,
+            None => {
+                warn!("ota: no image for {:?}", request);
+                return ImageBlockResponse::Abort;
+            }
+        };
+
+        let start = request.file_offset as usize;
+        if start >= image.data.len() {
+            warn!("ota: offset beyond end of image: {:?}", request);
+            return ImageBlockResponse::Abort;
+        }
+        let len = usize::from(request.max_data_size.min(MAX_BLOCK_SIZE));
+        let end = image.data.len().min(start + len);
+
+        debug!(
+            "ota: sending {} bytes at offset {} of {:?}",
+            end - start,
+            start,
+            image
+        );
+        ImageBlockResponse::Success {
+            manufacturer_code: image.manufacturer_code,
+            image_type: image.image_type,
+            file_version: image.file_version,
+            file_offset: request.file_offset,
+            data: image.data[start..end].to_vec(),
+        }
+    }
+
+    /// Tells the client to switch to the new image immediately, if it was downloaded successfully.
+    fn upgrade_end(&self, request: UpgradeEndRequest) -> Option<UpgradeEndResponse> {
+        if request.status != ZclStatus::Success {
+            warn!("ota: upgrade failed: {:?}", request);
+            return None;
+        }
+
+        info!("ota: upgrade complete: {:?}", request);
+        Some(UpgradeEndResponse {
+            manufacturer_code: request.manufacturer_code,
+            image_type: request.image_type,
+            file_version: request.file_version,
+            current_time: 0,
+            upgrade_time: 0,
+        })
+    }
+}
+
+fn to_payload<T>(value: T) -> Result<Vec<u8>>
+where
+    T: WriteWire<Error = Error>,
+{
+    let mut payload = Vec::new();
+    payload.write_wire(value)?;
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::tests::{image_bytes, IMAGE_TYPE, MANUFACTURER_CODE};
+    use super::*;
+
+    /// The image is 56 bytes of header followed by 100 bytes of data.
+    const IMAGE_SIZE: u32 = 156;
+
+    fn server() -> OtaServer {
+        // The server only needs the connection to send replies, which these tests don't.
+        let (deconz, aps_reader) = Deconz::new(tokio::io::empty(), tokio::io::sink());
+        let image = OtaImage::parse(image_bytes(0x00000002, 100)).unwrap();
+        OtaServer::new(deconz, aps_reader, vec![image])
+    }
+
+    fn request(file_offset: u32, max_data_size: u8) -> ImageBlockRequest {
+        ImageBlockRequest {
+            manufacturer_code: MANUFACTURER_CODE,
+            image_type: IMAGE_TYPE,
+            file_version: 0x00000002,
+            file_offset,
+            max_data_size,
+            request_node_address: None,
+            minimum_block_period: None,
+        }
+    }
+
+    fn block(response: ImageBlockResponse) -> (u32, Vec<u8>) {
+        match response {
+            ImageBlockResponse::Success {
+                file_offset, data, ..
+            } => (file_offset, data),
+            ImageBlockResponse::Abort => panic!("expected Success, got Abort"),
+        }
+    }
+
+    #[tokio::test]
+    async fn image_block() {
+        let (file_offset, data) = block(server().image_block(request(0, 32)));
+        assert_eq!(file_offset, 0);
+        assert_eq!(data.len(), 32);
+        assert_eq!(&data[..4], &[0x1E, 0xF1, 0xEE, 0x0B]);
+    }
+
+    #[tokio::test]
+    async fn last_partial_block() {
+        let (file_offset, data) = block(server().image_block(request(IMAGE_SIZE - 10, 64)));
+        assert_eq!(file_offset, IMAGE_SIZE - 10);
+        assert_eq!(data, (90..100).collect::<Vec<u8>>());
+    }
+
+    #[tokio::test]
+    async fn offset_beyond_end() {
+        let server = server();
+        for &file_offset in &[IMAGE_SIZE, IMAGE_SIZE + 1] {
+            match server.image_block(request(file_offset, 64)) {
+                ImageBlockResponse::Abort => {}
+                response => panic!("expected Abort, got {:?}", response),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn max_data_size_is_clamped() {
+        let (_, data) = block(server().image_block(request(0, 0xFF)));
+        assert_eq!(data.len(), usize::from(MAX_BLOCK_SIZE));
+    }
+
+    #[tokio::test]
+    async fn unknown_image_is_aborted() {
+        let mut request = request(0, 64);
+        request.file_version = 0x00000003;
+        match server().image_block(request) {
+            ImageBlockResponse::Abort => {}
+            response => panic!("expected Abort, got {:?}", response),
+        }
+    }
+}
@@ -0,0 +1,519 @@
+use std::fmt::{self, Debug};
+use std::io::{Cursor, Read, Write};
+
+use deconz::{ExtendedAddress, ReadWire, ReadWireExt, WriteWire, WriteWireExt};
+
+use crate::zcl::{Error, ErrorKind, Result, ZclStatus};
+
+/// Identifies an OTA upgrade file.
+const FILE_IDENTIFIER: u32 = 0x0BEE_F11E;
+
+/// The length of the mandatory fields of an OTA file header.
+const MIN_HEADER_LEN: usize = 56;
+
+/// A firmware image in the OTA upgrade file format, which is served to clients whole, including
+/// its header.
+#[derive(Clone)]
+pub struct OtaImage {
+    pub manufacturer_code: u16,
+    pub image_type: u16,
+    pub file_version: u32,
+    pub data: Vec<u8>,
+}
+
+impl OtaImage {
+    /// Parses the header of an OTA upgrade file.
+    pub fn parse(data: Vec<u8>) -> Result<Self> {
+        if data.len() < MIN_HEADER_LEN {
+            return Err(ErrorKind::InvalidOtaImage("truncated header").into());
+        }
+
+        let mut r = Cursor::new(&data);
+        let file_identifier: u32 = r.read_wire()?;
+        if file_identifier != FILE_IDENTIFIER {
+            return Err(ErrorKind::InvalidOtaImage("bad file identifier").into());
+        }
+        let _header_version: u16 = r.read_wire()?;
+        let _header_len: u16 = r.read_wire()?;
+        let _field_control: u16 = r.read_wire()?;
+        let manufacturer_code = r.read_wire()?;
+        let image_type = r.read_wire()?;
+        let file_version = r.read_wire()?;
+        let _stack_version: u16 = r.read_wire()?;
+        let mut header_string = [0; 32];
+        r.read_exact(&mut header_string)?;
+        let image_size: u32 = r.read_wire()?;
+        if image_size as usize != data.len() {
+            return Err(ErrorKind::InvalidOtaImage("size doesn't match header").into());
+        }
+
+        Ok(OtaImage {
+            manufacturer_code,
+            image_type,
+            file_version,
+            data,
+        })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.data.len() as u32
+    }
+
+    /// Whether the image is for the same kind of device as the request.
+    pub fn matches(&self, manufacturer_code: u16, image_type: u16) -> bool {
+        self.manufacturer_code == manufacturer_code && self.image_type == image_type
+    }
+}
+
+// The image itself is too large to be useful in logs.
+impl Debug for OtaImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtaImage")
+            .field("manufacturer_code", &self.manufacturer_code)
+            .field("image_type", &self.image_type)
+            .field("file_version", &self.file_version)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
+/// Sent by a client to ask whether there is a newer image than `current_file_version`.
+#[derive(Debug)]
+pub struct QueryNextImageRequest {
+    pub manufacturer_code: u16,
+    pub image_type: u16,
+    pub current_file_version: u32,
+    pub hardware_version: Option<u16>,
+}
+
+impl QueryNextImageRequest {
+    pub const COMMAND_ID: u8 = 0x01;
+}
+
+impl ReadWire for QueryNextImageRequest {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let field_control: u8 = r.read_wire()?;
+        let manufacturer_code = r.read_wire()?;
+        let image_type = r.read_wire()?;
+        let current_file_version = r.read_wire()?;
+        let hardware_version = match field_control & 0x01 {
+            0 => None,
+            _ => Some(r.read_wire()?),
+        };
+
+        Ok(QueryNextImageRequest {
+            manufacturer_code,
+            image_type,
+            current_file_version,
+            hardware_version,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum QueryNextImageResponse {
+    Available {
+        manufacturer_code: u16,
+        image_type: u16,
+        file_version: u32,
+        image_size: u32,
+    },
+    NoImageAvailable,
+}
+
+impl QueryNextImageResponse {
+    pub const COMMAND_ID: u8 = 0x02;
+}
+
+impl WriteWire for QueryNextImageResponse {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        match self {
+            QueryNextImageResponse::Available { .. } => 13,
+            QueryNextImageResponse::NoImageAvailable => 1,
+        }
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            QueryNextImageResponse::Available {
+                manufacturer_code,
+                image_type,
+                file_version,
+                image_size,
+            } => {
+                w.write_wire(ZclStatus::Success)?;
+                w.write_wire(manufacturer_code)?;
+                w.write_wire(image_type)?;
+                w.write_wire(file_version)?;
+                w.write_wire(image_size)?;
+            }
+            QueryNextImageResponse::NoImageAvailable => {
+                w.write_wire(ZclStatus::NoImageAvailable)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sent by a client to fetch up to `max_data_size` bytes of the image from `file_offset`.
+#[derive(Debug)]
+pub struct ImageBlockRequest {
+    pub manufacturer_code: u16,
+    pub image_type: u16,
+    pub file_version: u32,
+    pub file_offset: u32,
+    pub max_data_size: u8,
+    pub request_node_address: Option<ExtendedAddress>,
+    pub minimum_block_period: Option<u16>,
+}
+
+impl ImageBlockRequest {
+    pub const COMMAND_ID: u8 = 0x03;
+}
+
+impl ReadWire for ImageBlockRequest {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let field_control: u8 = r.read_wire()?;
+        let manufacturer_code = r.read_wire()?;
+        let image_type = r.read_wire()?;
+        let file_version = r.read_wire()?;
+        let file_offset = r.read_wire()?;
+        let max_data_size = r.read_wire()?;
+        let request_node_address = match field_control & 0x01 {
+            0 => None,
+            _ => Some(r.read_wire()?),
+        };
+        let minimum_block_period = match field_control & 0x02 {
+            0 => None,
+            _ => Some(r.read_wire()?),
+        };
+
+        Ok(ImageBlockRequest {
+            manufacturer_code,
+            image_type,
+            file_version,
+            file_offset,
+            max_data_size,
+            request_node_address,
+            minimum_block_period,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ImageBlockResponse {
+    Success {
+        manufacturer_code: u16,
+        image_type: u16,
+        file_version: u32,
+        file_offset: u32,
+        data: Vec<u8>,
+    },
+    /// The client should stop upgrading, e.g. because the image is no longer available.
+    Abort,
+}
+
+impl ImageBlockResponse {
+    pub const COMMAND_ID: u8 = 0x05;
+}
+
+impl WriteWire for ImageBlockResponse {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        match self {
+            ImageBlockResponse::Success { data, .. } => 14 + data.len() as u16,
+            ImageBlockResponse::Abort => 1,
+        }
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            ImageBlockResponse::Success {
+                manufacturer_code,
+                image_type,
+                file_version,
+                file_offset,
+                data,
+            } => {
+                w.write_wire(ZclStatus::Success)?;
+                w.write_wire(manufacturer_code)?;
+                w.write_wire(image_type)?;
+                w.write_wire(file_version)?;
+                w.write_wire(file_offset)?;
+                w.write_wire(data.len() as u8)?;
+                w.write_all(&data)?;
+            }
+            ImageBlockResponse::Abort => {
+                w.write_wire(ZclStatus::Abort)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sent by a client once it has downloaded the whole image, or has given up.
+#[derive(Debug)]
+pub struct UpgradeEndRequest {
+    pub status: ZclStatus,
+    pub manufacturer_code: u16,
+    pub image_type: u16,
+    pub file_version: u32,
+}
+
+impl UpgradeEndRequest {
+    pub const COMMAND_ID: u8 = 0x06;
+}
+
+impl ReadWire for UpgradeEndRequest {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(UpgradeEndRequest {
+            status: r.read_wire()?,
+            manufacturer_code: r.read_wire()?,
+            image_type: r.read_wire()?,
+            file_version: r.read_wire()?,
+        })
+    }
+}
+
+/// Tells the client when to switch to the new image. Both times are in UTC seconds since
+/// 2000-01-01; a current time of 0 makes `upgrade_time` an offset from now.
+#[derive(Debug)]
+pub struct UpgradeEndResponse {
+    pub manufacturer_code: u16,
+    pub image_type: u16,
+    pub file_version: u32,
+    pub current_time: u32,
+    pub upgrade_time: u32,
+}
+
+impl UpgradeEndResponse {
+    pub const COMMAND_ID: u8 = 0x07;
+}
+
+impl WriteWire for UpgradeEndResponse {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        16
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.manufacturer_code)?;
+        w.write_wire(self.image_type)?;
+        w.write_wire(self.file_version)?;
+        w.write_wire(self.current_time)?;
+        w.write_wire(self.upgrade_time)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const MANUFACTURER_CODE: u16 = 0x115F;
+    pub(crate) const IMAGE_TYPE: u16 = 0x1234;
+
+    /// An OTA upgrade file of `file_version` with `len` bytes of image data after its header.
+    pub(crate) fn image_bytes(file_version: u32, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.write_wire(FILE_IDENTIFIER).unwrap();
+        bytes.write_wire(0x0100u16).unwrap();
+        bytes.write_wire(MIN_HEADER_LEN as u16).unwrap();
+        bytes.write_wire(0x0000u16).unwrap();
+        bytes.write_wire(MANUFACTURER_CODE).unwrap();
+        bytes.write_wire(IMAGE_TYPE).unwrap();
+        bytes.write_wire(file_version).unwrap();
+        bytes.write_wire(0x0002u16).unwrap();
+        bytes.extend(&[0; 32]);
+        bytes.write_wire((MIN_HEADER_LEN + len) as u32).unwrap();
+        bytes.extend((0..len).map(|i| i as u8));
+        bytes
+    }
+
+    fn encode<T: WriteWire<Error = Error>>(value: T) -> Vec<u8> {
+        let len = value.wire_len();
+        let mut buffer = Vec::new();
+        buffer.write_wire(value).unwrap();
+        assert_eq!(buffer.len(), usize::from(len));
+        buffer
+    }
+
+    fn assert_invalid(result: Result<OtaImage>, reason: &str) {
+        match result {
+            Err(Error {
+                kind: ErrorKind::InvalidOtaImage(actual),
+            }) => assert_eq!(actual, reason),
+            result => panic!("expected InvalidOtaImage, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parse_image() {
+        let image = OtaImage::parse(image_bytes(0x00000002, 100)).unwrap();
+        assert_eq!(image.manufacturer_code, MANUFACTURER_CODE);
+        assert_eq!(image.image_type, IMAGE_TYPE);
+        assert_eq!(image.file_version, 0x00000002);
+        assert_eq!(image.size(), 156);
+    }
+
+    #[test]
+    fn parse_bad_identifier() {
+        let mut bytes = image_bytes(0x00000002, 100);
+        bytes[0] = 0x00;
+        assert_invalid(OtaImage::parse(bytes), "bad file identifier");
+    }
+
+    #[test]
+    fn parse_truncated_header() {
+        let mut bytes = image_bytes(0x00000002, 0);
+        bytes.pop();
+        assert_invalid(OtaImage::parse(bytes), "truncated header");
+    }
+
+    #[test]
+    fn parse_size_mismatch() {
+        let mut bytes = image_bytes(0x00000002, 100);
+        bytes.pop();
+        assert_invalid(OtaImage::parse(bytes), "size doesn't match header");
+    }
+
+    #[test]
+    fn query_next_image_request() {
+        let bytes = [0x00, 0x5F, 0x11, 0x34, 0x12, 0x01, 0x00, 0x00, 0x00];
+        let request = QueryNextImageRequest::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(request.manufacturer_code, MANUFACTURER_CODE);
+        assert_eq!(request.image_type, IMAGE_TYPE);
+        assert_eq!(request.current_file_version, 0x00000001);
+        assert_eq!(request.hardware_version, None);
+    }
+
+    #[test]
+    fn query_next_image_request_with_hardware_version() {
+        let bytes = [
+            0x01, 0x5F, 0x11, 0x34, 0x12, 0x01, 0x00, 0x00, 0x00, 0x03, 0x00,
+        ];
+        let request = QueryNextImageRequest::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(request.current_file_version, 0x00000001);
+        assert_eq!(request.hardware_version, Some(0x0003));
+    }
+
+    #[test]
+    fn image_block_request() {
+        let bytes = [
+            0x00, 0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x30,
+        ];
+        let request = ImageBlockRequest::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(request.manufacturer_code, MANUFACTURER_CODE);
+        assert_eq!(request.image_type, IMAGE_TYPE);
+        assert_eq!(request.file_version, 0x00000002);
+        assert_eq!(request.file_offset, 0x40);
+        assert_eq!(request.max_data_size, 0x30);
+        assert_eq!(request.request_node_address, None);
+        assert_eq!(request.minimum_block_period, None);
+    }
+
+    #[test]
+    fn image_block_request_with_optional_fields() {
+        let bytes = [
+            0x03, 0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x30,
+            0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00, 0xE8, 0x03,
+        ];
+        let request = ImageBlockRequest::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(
+            request.request_node_address,
+            Some(ExtendedAddress(0x00212effff012345))
+        );
+        assert_eq!(request.minimum_block_period, Some(1000));
+    }
+
+    #[test]
+    fn image_block_request_with_minimum_block_period() {
+        let bytes = [
+            0x02, 0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x30,
+            0xE8, 0x03,
+        ];
+        let request = ImageBlockRequest::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(request.request_node_address, None);
+        assert_eq!(request.minimum_block_period, Some(1000));
+    }
+
+    #[test]
+    fn query_next_image_responses() {
+        let response = QueryNextImageResponse::Available {
+            manufacturer_code: MANUFACTURER_CODE,
+            image_type: IMAGE_TYPE,
+            file_version: 0x00000002,
+            image_size: 156,
+        };
+        assert_eq!(
+            encode(response),
+            [0x00, 0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x9C, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(encode(QueryNextImageResponse::NoImageAvailable), [0x98]);
+    }
+
+    #[test]
+    fn image_block_responses() {
+        let response = ImageBlockResponse::Success {
+            manufacturer_code: MANUFACTURER_CODE,
+            image_type: IMAGE_TYPE,
+            file_version: 0x00000002,
+            file_offset: 0x40,
+            data: vec![0xAA, 0xBB],
+        };
+        assert_eq!(
+            encode(response),
+            [
+                0x00, 0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x02,
+                0xAA, 0xBB
+            ]
+        );
+        assert_eq!(encode(ImageBlockResponse::Abort), [0x95]);
+    }
+
+    #[test]
+    fn upgrade_end_response() {
+        let response = UpgradeEndResponse {
+            manufacturer_code: MANUFACTURER_CODE,
+            image_type: IMAGE_TYPE,
+            file_version: 0x00000002,
+            current_time: 0,
+            upgrade_time: 0,
+        };
+        assert_eq!(
+            encode(response),
+            [
+                0x5F, 0x11, 0x34, 0x12, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00
+            ]
+        );
+    }
+}
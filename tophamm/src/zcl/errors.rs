@@ -0,0 +1,90 @@
+use std::fmt::{self, Display};
+use std::io;
+
+use tokio::sync::oneshot;
+
+use super::ZclStatus;
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    Deconz(deconz::Error),
+    Io(io::Error),
+    UnexpectedCommand(u8),
+    UnsupportedDataType(u8),
+    UnsupportedReportingDirection(u8),
+    InvalidOtaImage(&'static str),
+    UnsupportedGpdApplicationId(u8),
+    ZclFailure(ZclStatus),
+    Timeout,
+    ChannelError,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Deconz(error) => write!(f, "deconz: {}", error),
+            ErrorKind::Io(error) => write!(f, "io: {}", error),
+            ErrorKind::UnexpectedCommand(command_id) => {
+                write!(f, "unexpected command ID in response: {:#04x}", command_id)
+            }
+            ErrorKind::UnsupportedDataType(data_type) => {
+                write!(f, "unsupported data type: {:#04x}", data_type)
+            }
+            ErrorKind::UnsupportedReportingDirection(direction) => {
+                write!(f, "unsupported reporting direction: {:#04x}", direction)
+            }
+            ErrorKind::InvalidOtaImage(reason) => write!(f, "invalid OTA image: {}", reason),
+            ErrorKind::UnsupportedGpdApplicationId(application_id) => {
+                write!(f, "unsupported GPD application ID: {}", application_id)
+            }
+            ErrorKind::ZclFailure(status) => write!(f, "unsuccessful status: {:?}", status),
+            ErrorKind::Timeout => write!(f, "timeout"),
+            ErrorKind::ChannelError => write!(f, "channel error"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub kind: ErrorKind,
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<deconz::Error> for Error {
+    fn from(other: deconz::Error) -> Self {
+        Error {
+            kind: ErrorKind::Deconz(other),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(other: io::Error) -> Self {
+        Error {
+            kind: ErrorKind::Io(other),
+        }
+    }
+}
+
+impl From<oneshot::error::RecvError> for Error {
+    fn from(_: oneshot::error::RecvError) -> Error {
+        Error {
+            kind: ErrorKind::ChannelError,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,325 @@
+mod errors;
+pub mod protocol;
+pub mod reports;
+
+use std::io::Cursor;
+use std::time::Duration;
+
+use deconz::*;
+use tokio::stream::StreamExt;
+use tokio::sync::{mpsc, oneshot};
+use tophamm_helpers::{awaiting, IncrementingId};
+
+use self::protocol::{
+    ConfigureReportingRequest, DefaultResponse, Direction, Header, ReadAttributesRequest,
+    ReportAttributes,
+};
+
+pub use self::errors::{Error, ErrorKind, Result};
+pub use self::protocol::{
+    AttributeRecord, AttributeReportingConfig, AttributeStatusRecord, ZclFrame, ZclStatus,
+};
+pub use self::reports::{is_attribute_report, ApsReaderExt};
+
+type TransactionId = u8;
+
+/// The endpoint on the adapter that ZCL requests are sent from, and so responses are sent to.
+const SOURCE_ENDPOINT: Endpoint = Endpoint(0x01);
+
+/// Wait at most this long for a response to a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub trait Request: WriteWire {
+    const COMMAND_ID: u8;
+
+    type Response: Response;
+
+    fn cluster_id(&self) -> ClusterId;
+}
+
+pub trait Response: ReadWire {
+    const COMMAND_ID: u8;
+}
+
+type ZclRequest = (
+    TransactionId,
+    ApsDataRequest,
+    oneshot::Sender<Result<ApsDataIndication>>,
+);
+
+type Awaiting = awaiting::Awaiting<TransactionId, ApsDataIndication, Error>;
+
+pub struct Zcl {
+    requests: mpsc::Sender<ZclRequest>,
+    transaction_ids: IncrementingId,
+}
+
+impl Zcl {
+    pub fn new(deconz: Deconz, aps_data_indications: ApsReader) -> Self {
+        let (requests_tx, requests) = mpsc::channel(1);
+
+        let awaiting = Awaiting::new();
+        let rx = Rx {
+            awaiting: awaiting.clone(),
+            aps_data_indications,
+        };
+        let tx = Tx {
+            deconz,
+            awaiting,
+            requests,
+        };
+
+        tokio::spawn(rx.task());
+        tokio::spawn(tx.task());
+
+        Self {
+            requests: requests_tx,
+            transaction_ids: IncrementingId::new(),
+        }
+    }
+
+    fn make_aps_data_request(
+        &self,
+        destination: Destination,
+        cluster_id: ClusterId,
+        frame: ZclFrame,
+    ) -> Result<ApsDataRequest> {
+        let mut asdu = Vec::new();
+        asdu.write_wire(frame)?;
+
+        let request = ApsDataRequest::builder()
+            .destination(destination)
+            // All of the ZCL clusters we use belong to the Home Automation profile.
+            .profile(ProfileId::HA)
+            .cluster(cluster_id)
+            .source_endpoint(SOURCE_ENDPOINT)
+            .asdu(asdu)
+            .build()?;
+        Ok(request)
+    }
+
+    pub async fn make_request<R>(&self, destination: Destination, request: R) -> Result<R::Response>
+    where
+        R: Request,
+        Error: From<R::Error>,
+        Error: From<<R::Response as ReadWire>::Error>,
+    {
+        let cluster_id = request.cluster_id();
+        let mut payload = Vec::new();
+        payload.write_wire(request)?;
+
+        let frame = ZclFrame::global(R::COMMAND_ID, payload);
+        let frame = self.send_frame(destination, cluster_id, frame).await?;
+        if frame.command_id != R::Response::COMMAND_ID {
+            return Err(ErrorKind::UnexpectedCommand(frame.command_id).into());
+        }
+        let response = Cursor::new(&frame.payload).read_wire()?;
+
+        Ok(response)
+    }
+
+    /// Sends `frame` to the cluster and waits for the response, which is returned unless it is an
+    /// unsuccessful default response. Fails with `ErrorKind::Timeout` if no response arrives in
+    /// time. The frame's `transaction_seq` is replaced.
+    ///
+    /// A response is always awaited, so the default response shouldn't be disabled for commands
+    /// that have no specific response.
+    pub async fn send_frame(
+        &self,
+        destination: Destination,
+        cluster_id: ClusterId,
+        mut frame: ZclFrame,
+    ) -> Result<ZclFrame> {
+        let id = self.transaction_ids.next();
+        frame.transaction_seq = id;
+        let request = self.make_aps_data_request(destination, cluster_id, frame)?;
+
+        let (sender, receiver) = oneshot::channel();
+        self.requests
+            .clone()
+            .send((id, request, sender))
+            .await
+            .map_err(|_| ErrorKind::ChannelError)?;
+
+        let result = receiver.await?;
+        let aps_data_indication = result?;
+
+        parse_response(&aps_data_indication.asdu)
+    }
+}
+
+/// Parses the frame sent in response to a request, failing if it is an unsuccessful default
+/// response.
+fn parse_response(asdu: &[u8]) -> Result<ZclFrame> {
+    let frame: ZclFrame = Cursor::new(asdu).read_wire()?;
+    if frame.is_default_response() {
+        let default_response: DefaultResponse = Cursor::new(&frame.payload).read_wire()?;
+        if default_response.status != ZclStatus::Success {
+            return Err(ErrorKind::ZclFailure(default_response.status).into());
+        }
+    }
+
+    Ok(frame)
+}
+
+struct Rx {
+    awaiting: Awaiting,
+    aps_data_indications: ApsReader,
+}
+
+impl Rx {
+    async fn task(mut self) -> Result<()> {
+        while let Some(aps_data_indication) = self.aps_data_indications.next().await {
+            let header: Header = match Cursor::new(&aps_data_indication.asdu).read_wire() {
+                Ok(header) => header,
+                Err(error) => {
+                    error!("zcl rx: invalid header: {}", error);
+                    continue;
+                }
+            };
+
+            // Commands and reports sent by devices carry their own transaction sequence numbers,
+            // which could otherwise be mistaken for the response to one of our requests.
+            if !is_response(&header) {
+                debug!("zcl rx: ignoring frame that isn't a response: {:?}", header);
+                continue;
+            }
+
+            let id = header.transaction_id;
+            if let Some(Ok(unsolicited)) = self.awaiting.send(&id, Ok(aps_data_indication)) {
+                error!("zcl rx: unexpected frame: {:?}", unsolicited);
+            }
+        }
+
+        // No more responses will arrive.
+        self.awaiting.cancel_all(|| ErrorKind::ChannelError.into());
+
+        Ok(())
+    }
+}
+
+/// Whether a frame with this header could be the response to a request sent by [`Zcl`].
+fn is_response(header: &Header) -> bool {
+    let is_report = !header.frame_control.is_cluster_specific()
+        && header.command_id == ReportAttributes::COMMAND_ID;
+    header.frame_control.direction() == Direction::ServerToClient && !is_report
+}
+
+struct Tx {
+    deconz: Deconz,
+    awaiting: Awaiting,
+    requests: mpsc::Receiver<ZclRequest>,
+}
+
+impl Tx {
+    async fn task(mut self) -> Result<()> {
+        while let Some((id, request, sender)) = self.requests.next().await {
+            // Devices may never respond (e.g. because they've left the network), so don't leave
+            // the caller waiting forever.
+            let deconz = self.deconz.clone();
+            let future = async move { deconz.aps_data_request(request).await };
+            tokio::spawn(self.awaiting.clone().register_while_timeout(
+                id,
+                sender,
+                future,
+                REQUEST_TIMEOUT,
+                ErrorKind::Timeout.into(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// Higher-level helpers.
+impl Zcl {
+    /// Sends a cluster-specific command from client to server, e.g. On/Off's Toggle (0x02).
+    pub async fn cluster_command(
+        &self,
+        destination: Destination,
+        cluster: ClusterId,
+        command_id: u8,
+        payload: Vec<u8>,
+    ) -> Result<ZclFrame> {
+        let frame = ZclFrame::cluster_specific(command_id, payload);
+        self.send_frame(destination, cluster, frame).await
+    }
+
+    pub async fn read_attributes(
+        &self,
+        destination: Destination,
+        cluster: ClusterId,
+        attribute_ids: Vec<u16>,
+    ) -> Result<Vec<AttributeRecord>> {
+        let request = ReadAttributesRequest {
+            cluster,
+            attribute_ids,
+        };
+        let resp = self.make_request(destination, request).await?;
+        Ok(resp.records)
+    }
+
+    /// Configures how the device reports attributes of `cluster`, returning the records of any
+    /// attributes that couldn't be configured.
+    pub async fn configure_reporting(
+        &self,
+        destination: Destination,
+        cluster: ClusterId,
+        records: Vec<AttributeReportingConfig>,
+    ) -> Result<Vec<AttributeStatusRecord>> {
+        let request = ConfigureReportingRequest { cluster, records };
+        let resp = self.make_request(destination, request).await?;
+        Ok(resp.records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsuccessful_default_response() {
+        // A Default Response to Read Attributes (0x00) with the status UNSUPPORTED_ATTRIBUTE.
+        let asdu = [0x18, 0x07, 0x0B, 0x00, 0x86];
+        match parse_response(&asdu) {
+            Err(Error {
+                kind: ErrorKind::ZclFailure(ZclStatus::UnsupportedAttribute),
+            }) => {}
+            result => panic!("expected ZclFailure, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn successful_default_response() {
+        // A Default Response to On/Off's Toggle (0x02).
+        let asdu = [0x18, 0x07, 0x0B, 0x02, 0x00];
+        let frame = parse_response(&asdu).unwrap();
+        assert!(frame.is_default_response());
+        assert_eq!(frame.transaction_seq, 0x07);
+    }
+
+    fn header(asdu: &[u8]) -> Header {
+        Cursor::new(asdu).read_wire().unwrap()
+    }
+
+    #[test]
+    fn responses_are_matched() {
+        // Read Attributes Response and Default Response.
+        assert!(is_response(&header(&[0x18, 0x07, 0x01])));
+        assert!(is_response(&header(&[0x18, 0x07, 0x0B])));
+    }
+
+    #[test]
+    fn reports_are_not_responses() {
+        // Report Attributes, with and without default responses disabled.
+        assert!(!is_response(&header(&[0x18, 0x07, 0x0A])));
+        assert!(!is_response(&header(&[0x08, 0x07, 0x0A])));
+    }
+
+    #[test]
+    fn client_to_server_commands_are_not_responses() {
+        // Read Attributes and a cluster-specific command sent by a device.
+        assert!(!is_response(&header(&[0x00, 0x07, 0x00])));
+        assert!(!is_response(&header(&[0x01, 0x07, 0x02])));
+    }
+}
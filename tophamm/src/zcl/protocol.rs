@@ -0,0 +1,999 @@
+use std::fmt::{self, Debug};
+use std::io::{Cursor, Read, Write};
+
+use deconz::{ClusterId, ReadWire, ReadWireExt, WriteWire, WriteWireExt};
+
+use super::{Error, ErrorKind, Request, Response, Result};
+
+/// The frame control field of a ZCL header, whose flags can be combined with `|`. The empty frame
+/// control is for a profile-wide (global) command sent from client to server.
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct FrameControl(u8);
+
+impl FrameControl {
+    /// The command is specific to the cluster, rather than global.
+    pub const CLUSTER_SPECIFIC: FrameControl = FrameControl(0x01);
+    /// The header includes a manufacturer code. `Header` sets this when writing, based on
+    /// whether it has a manufacturer code.
+    pub const MANUFACTURER_SPECIFIC: FrameControl = FrameControl(0x04);
+    /// The command is sent from the server side of the cluster to the client side.
+    pub const SERVER_TO_CLIENT: FrameControl = FrameControl(0x08);
+    /// The receiver shouldn't send a default response, unless the command fails.
+    pub const DISABLE_DEFAULT_RESPONSE: FrameControl = FrameControl(0x10);
+
+    pub fn empty() -> Self {
+        FrameControl(0)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: FrameControl) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn is_cluster_specific(self) -> bool {
+        self.contains(FrameControl::CLUSTER_SPECIFIC)
+    }
+
+    pub fn direction(self) -> Direction {
+        match self.contains(FrameControl::SERVER_TO_CLIENT) {
+            true => Direction::ServerToClient,
+            false => Direction::ClientToServer,
+        }
+    }
+
+    pub fn disable_default_response(self) -> bool {
+        self.contains(FrameControl::DISABLE_DEFAULT_RESPONSE)
+    }
+
+    fn with(self, other: FrameControl, set: bool) -> Self {
+        match set {
+            true => FrameControl(self.0 | other.0),
+            false => FrameControl(self.0 & !other.0),
+        }
+    }
+}
+
+impl std::ops::BitOr for FrameControl {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        FrameControl(self.0 | other.0)
+    }
+}
+
+impl Debug for FrameControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FrameControl({:#04x})", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+#[derive(Debug)]
+pub struct Header {
+    pub frame_control: FrameControl,
+    pub manufacturer_code: Option<u16>,
+    pub transaction_id: u8,
+    pub command_id: u8,
+}
+
+impl ReadWire for Header {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let frame_control = FrameControl(r.read_wire()?);
+        let manufacturer_code = match frame_control.contains(FrameControl::MANUFACTURER_SPECIFIC) {
+            true => Some(r.read_wire()?),
+            false => None,
+        };
+        let transaction_id = r.read_wire()?;
+        let command_id = r.read_wire()?;
+
+        Ok(Header {
+            frame_control,
+            manufacturer_code,
+            transaction_id,
+            command_id,
+        })
+    }
+}
+
+impl WriteWire for Header {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        match self.manufacturer_code {
+            Some(_) => 5,
+            None => 3,
+        }
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let manufacturer_specific = self.manufacturer_code.is_some();
+        let frame_control = self
+            .frame_control
+            .with(FrameControl::MANUFACTURER_SPECIFIC, manufacturer_specific);
+        w.write_wire(frame_control.bits())?;
+        if let Some(manufacturer_code) = self.manufacturer_code {
+            w.write_wire(manufacturer_code)?;
+        }
+        w.write_wire(self.transaction_id)?;
+        w.write_wire(self.command_id)?;
+        Ok(())
+    }
+}
+
+/// A complete ZCL frame: the header, followed by the command's payload.
+#[derive(Clone, Debug)]
+pub struct ZclFrame {
+    pub frame_control: FrameControl,
+    pub manufacturer_code: Option<u16>,
+    pub transaction_seq: u8,
+    pub command_id: u8,
+    pub payload: Vec<u8>,
+}
+
+impl ZclFrame {
+    /// A profile-wide command, such as Read Attributes, sent from client to server.
+    pub fn global(command_id: u8, payload: Vec<u8>) -> Self {
+        ZclFrame {
+            frame_control: FrameControl::empty(),
+            manufacturer_code: None,
+            transaction_seq: 0,
+            command_id,
+            payload,
+        }
+    }
+
+    /// A command specific to the cluster, such as On/Off's Toggle, sent from client to server.
+    pub fn cluster_specific(command_id: u8, payload: Vec<u8>) -> Self {
+        ZclFrame {
+            frame_control: FrameControl::CLUSTER_SPECIFIC,
+            ..ZclFrame::global(command_id, payload)
+        }
+    }
+
+    pub fn is_default_response(&self) -> bool {
+        !self.frame_control.is_cluster_specific() && self.command_id == DefaultResponse::COMMAND_ID
+    }
+}
+
+impl ReadWire for ZclFrame {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let header: Header = r.read_wire()?;
+        let mut payload = Vec::new();
+        r.read_to_end(&mut payload)?;
+
+        Ok(ZclFrame {
+            frame_control: header.frame_control,
+            manufacturer_code: header.manufacturer_code,
+            transaction_seq: header.transaction_id,
+            command_id: header.command_id,
+            payload,
+        })
+    }
+}
+
+impl WriteWire for ZclFrame {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        let header_len = match self.manufacturer_code {
+            Some(_) => 5,
+            None => 3,
+        };
+        header_len + self.payload.len() as u16
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(Header {
+            frame_control: self.frame_control,
+            manufacturer_code: self.manufacturer_code,
+            transaction_id: self.transaction_seq,
+            command_id: self.command_id,
+        })?;
+        w.write_all(&self.payload)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ZclStatus {
+    Success,
+    Failure,
+    NotAuthorized,
+    MalformedCommand,
+    UnsupportedCommand,
+    UnsupportedAttribute,
+    InvalidValue,
+    ReadOnly,
+    InsufficientSpace,
+    NotFound,
+    /// OTA: the client should abort the upgrade.
+    Abort,
+    /// OTA: the client should wait before requesting more of the image.
+    WaitForData,
+    /// OTA: the server has no new image for the client.
+    NoImageAvailable,
+    Unknown(u8),
+}
+
+impl From<u8> for ZclStatus {
+    fn from(status: u8) -> Self {
+        match status {
+            0x00 => ZclStatus::Success,
+            0x01 => ZclStatus::Failure,
+            0x7E => ZclStatus::NotAuthorized,
+            0x80 => ZclStatus::MalformedCommand,
+            0x81 => ZclStatus::UnsupportedCommand,
+            0x86 => ZclStatus::UnsupportedAttribute,
+            0x87 => ZclStatus::InvalidValue,
+            0x88 => ZclStatus::ReadOnly,
+            0x89 => ZclStatus::InsufficientSpace,
+            0x8B => ZclStatus::NotFound,
+            0x95 => ZclStatus::Abort,
+            0x97 => ZclStatus::WaitForData,
+            0x98 => ZclStatus::NoImageAvailable,
+            unknown => ZclStatus::Unknown(unknown),
+        }
+    }
+}
+
+impl From<ZclStatus> for u8 {
+    fn from(status: ZclStatus) -> Self {
+        match status {
+            ZclStatus::Success => 0x00,
+            ZclStatus::Failure => 0x01,
+            ZclStatus::NotAuthorized => 0x7E,
+            ZclStatus::MalformedCommand => 0x80,
+            ZclStatus::UnsupportedCommand => 0x81,
+            ZclStatus::UnsupportedAttribute => 0x86,
+            ZclStatus::InvalidValue => 0x87,
+            ZclStatus::ReadOnly => 0x88,
+            ZclStatus::InsufficientSpace => 0x89,
+            ZclStatus::NotFound => 0x8B,
+            ZclStatus::Abort => 0x95,
+            ZclStatus::WaitForData => 0x97,
+            ZclStatus::NoImageAvailable => 0x98,
+            ZclStatus::Unknown(unknown) => unknown,
+        }
+    }
+}
+
+impl ReadWire for ZclStatus {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        Ok(ZclStatus::from(u8::read_wire(r)?))
+    }
+}
+
+impl WriteWire for ZclStatus {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(u8::from(self))?;
+        Ok(())
+    }
+}
+
+/// Sent by a device in response to a command that has no specific response, or on failure.
+#[derive(Debug)]
+pub struct DefaultResponse {
+    pub command_id: u8,
+    pub status: ZclStatus,
+}
+
+impl DefaultResponse {
+    pub const COMMAND_ID: u8 = 0x0B;
+}
+
+impl ReadWire for DefaultResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let command_id = r.read_wire()?;
+        let status = r.read_wire()?;
+        Ok(DefaultResponse { command_id, status })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    Bool(bool),
+    Bitmap8(u8),
+    Bitmap16(u16),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    Enum8(u8),
+    Enum16(u16),
+    OctetString(Vec<u8>),
+    CharacterString(String),
+}
+
+impl AttributeValue {
+    pub fn data_type(&self) -> u8 {
+        match self {
+            AttributeValue::Bool(_) => 0x10,
+            AttributeValue::Bitmap8(_) => 0x18,
+            AttributeValue::Bitmap16(_) => 0x19,
+            AttributeValue::U8(_) => 0x20,
+            AttributeValue::U16(_) => 0x21,
+            AttributeValue::U32(_) => 0x23,
+            AttributeValue::I8(_) => 0x28,
+            AttributeValue::I16(_) => 0x29,
+            AttributeValue::I32(_) => 0x2b,
+            AttributeValue::Enum8(_) => 0x30,
+            AttributeValue::Enum16(_) => 0x31,
+            AttributeValue::OctetString(_) => 0x41,
+            AttributeValue::CharacterString(_) => 0x42,
+        }
+    }
+
+    /// Reads a value of the given `data_type`. Values of unknown types can't be skipped, as their
+    /// length isn't known.
+    fn read_typed<R>(r: &mut R, data_type: u8) -> Result<Self>
+    where
+        R: Read,
+    {
+        let value = match data_type {
+            0x10 => AttributeValue::Bool(r.read_wire()?),
+            0x18 => AttributeValue::Bitmap8(r.read_wire()?),
+            0x19 => AttributeValue::Bitmap16(r.read_wire()?),
+            0x20 => AttributeValue::U8(r.read_wire()?),
+            0x21 => AttributeValue::U16(r.read_wire()?),
+            0x23 => AttributeValue::U32(r.read_wire()?),
+            0x28 => AttributeValue::I8(r.read_wire()?),
+            0x29 => AttributeValue::I16(r.read_wire()?),
+            0x2b => AttributeValue::I32(r.read_wire()?),
+            0x30 => AttributeValue::Enum8(r.read_wire()?),
+            0x31 => AttributeValue::Enum16(r.read_wire()?),
+            0x41 => AttributeValue::OctetString(read_string(r)?),
+            0x42 => {
+                let bytes = read_string(r)?;
+                AttributeValue::CharacterString(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            unknown => return Err(ErrorKind::UnsupportedDataType(unknown).into()),
+        };
+        Ok(value)
+    }
+
+    /// The length of the value when written, excluding its data type.
+    fn value_len(&self) -> u16 {
+        match self {
+            AttributeValue::Bool(_)
+            | AttributeValue::Bitmap8(_)
+            | AttributeValue::U8(_)
+            | AttributeValue::I8(_)
+            | AttributeValue::Enum8(_) => 1,
+            AttributeValue::Bitmap16(_)
+            | AttributeValue::U16(_)
+            | AttributeValue::I16(_)
+            | AttributeValue::Enum16(_) => 2,
+            AttributeValue::U32(_) | AttributeValue::I32(_) => 4,
+            AttributeValue::OctetString(bytes) => 1 + bytes.len() as u16,
+            AttributeValue::CharacterString(string) => 1 + string.len() as u16,
+        }
+    }
+
+    /// Writes the value without its data type, which is written separately where needed.
+    fn write_value<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            AttributeValue::Bool(value) => w.write_wire(value)?,
+            AttributeValue::Bitmap8(value) => w.write_wire(value)?,
+            AttributeValue::Bitmap16(value) => w.write_wire(value)?,
+            AttributeValue::U8(value) => w.write_wire(value)?,
+            AttributeValue::U16(value) => w.write_wire(value)?,
+            AttributeValue::U32(value) => w.write_wire(value)?,
+            AttributeValue::I8(value) => w.write_wire(value)?,
+            AttributeValue::I16(value) => w.write_wire(value)?,
+            AttributeValue::I32(value) => w.write_wire(value)?,
+            AttributeValue::Enum8(value) => w.write_wire(value)?,
+            AttributeValue::Enum16(value) => w.write_wire(value)?,
+            AttributeValue::OctetString(bytes) => write_string(w, &bytes)?,
+            AttributeValue::CharacterString(string) => write_string(w, string.as_bytes())?,
+        }
+        Ok(())
+    }
+}
+
+fn write_string<W>(w: &mut W, bytes: &[u8]) -> Result<()>
+where
+    W: Write,
+{
+    w.write_wire(bytes.len() as u8)?;
+    w.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_string<R>(r: &mut R) -> Result<Vec<u8>>
+where
+    R: Read,
+{
+    let len: u8 = r.read_wire()?;
+    let mut bytes = vec![0; usize::from(len)];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[derive(Debug)]
+pub struct ReadAttributesRequest {
+    pub cluster: ClusterId,
+    pub attribute_ids: Vec<u16>,
+}
+
+impl Request for ReadAttributesRequest {
+    const COMMAND_ID: u8 = 0x00;
+
+    type Response = ReadAttributesResponse;
+
+    fn cluster_id(&self) -> ClusterId {
+        self.cluster
+    }
+}
+
+impl WriteWire for ReadAttributesRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2 * self.attribute_ids.len() as u16
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        for attribute_id in self.attribute_ids {
+            w.write_wire(attribute_id)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct AttributeRecord {
+    pub attribute_id: u16,
+    pub status: ZclStatus,
+    /// Only present when `status` is `ZclStatus::Success`.
+    pub value: Option<AttributeValue>,
+}
+
+#[derive(Debug)]
+pub struct ReadAttributesResponse {
+    pub records: Vec<AttributeRecord>,
+}
+
+impl Response for ReadAttributesResponse {
+    const COMMAND_ID: u8 = 0x01;
+}
+
+impl ReadWire for ReadAttributesResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut remaining = Vec::new();
+        r.read_to_end(&mut remaining)?;
+        let len = remaining.len() as u64;
+        let mut r = Cursor::new(remaining);
+
+        let mut records = Vec::new();
+        while r.position() < len {
+            let attribute_id = r.read_wire()?;
+            let status = r.read_wire()?;
+            let value = match status {
+                ZclStatus::Success => {
+                    let data_type = r.read_wire()?;
+                    Some(AttributeValue::read_typed(&mut r, data_type)?)
+                }
+                _ => None,
+            };
+            records.push(AttributeRecord {
+                attribute_id,
+                status,
+                value,
+            });
+        }
+
+        Ok(ReadAttributesResponse { records })
+    }
+}
+
+/// How an attribute should be reported, as configured by `ConfigureReportingRequest`.
+#[derive(Clone, Debug)]
+pub enum AttributeReportingConfig {
+    /// The device should report the attribute at least every `max_interval` seconds, but no more
+    /// often than every `min_interval` seconds. A max interval of 0xFFFF disables periodic
+    /// reports.
+    Reported {
+        attribute_id: u16,
+        data_type: u8,
+        min_interval: u16,
+        max_interval: u16,
+        /// How much the attribute must change by before it is reported. Must be set for analog
+        /// data types (integers), and not for discrete ones (e.g. bools, bitmaps and enums).
+        reportable_change: Option<AttributeValue>,
+    },
+    /// The device should expect to receive reports of the attribute at least every `timeout`
+    /// seconds, or 0 to not expect them periodically.
+    Received { attribute_id: u16, timeout: u16 },
+}
+
+impl AttributeReportingConfig {
+    fn direction(&self) -> ReportingDirection {
+        match self {
+            AttributeReportingConfig::Reported { .. } => ReportingDirection::Reported,
+            AttributeReportingConfig::Received { .. } => ReportingDirection::Received,
+        }
+    }
+}
+
+impl WriteWire for AttributeReportingConfig {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        match self {
+            AttributeReportingConfig::Reported {
+                reportable_change, ..
+            } => {
+                8 + reportable_change
+                    .as_ref()
+                    .map_or(0, AttributeValue::value_len)
+            }
+            AttributeReportingConfig::Received { .. } => 5,
+        }
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.direction())?;
+        match self {
+            AttributeReportingConfig::Reported {
+                attribute_id,
+                data_type,
+                min_interval,
+                max_interval,
+                reportable_change,
+            } => {
+                w.write_wire(attribute_id)?;
+                w.write_wire(data_type)?;
+                w.write_wire(min_interval)?;
+                w.write_wire(max_interval)?;
+                if let Some(reportable_change) = reportable_change {
+                    reportable_change.write_value(w)?;
+                }
+            }
+            AttributeReportingConfig::Received {
+                attribute_id,
+                timeout,
+            } => {
+                w.write_wire(attribute_id)?;
+                w.write_wire(timeout)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReportingDirection {
+    /// Reports of the attribute are sent by the device receiving the command.
+    Reported,
+    /// Reports of the attribute are received by the device receiving the command.
+    Received,
+}
+
+impl ReadWire for ReportingDirection {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        match u8::read_wire(r)? {
+            0x00 => Ok(ReportingDirection::Reported),
+            0x01 => Ok(ReportingDirection::Received),
+            unknown => Err(ErrorKind::UnsupportedReportingDirection(unknown).into()),
+        }
+    }
+}
+
+impl WriteWire for ReportingDirection {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let direction: u8 = match self {
+            ReportingDirection::Reported => 0x00,
+            ReportingDirection::Received => 0x01,
+        };
+        w.write_wire(direction)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigureReportingRequest {
+    pub cluster: ClusterId,
+    pub records: Vec<AttributeReportingConfig>,
+}
+
+impl Request for ConfigureReportingRequest {
+    const COMMAND_ID: u8 = 0x06;
+
+    type Response = ConfigureReportingResponse;
+
+    fn cluster_id(&self) -> ClusterId {
+        self.cluster
+    }
+}
+
+impl WriteWire for ConfigureReportingRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        self.records.iter().map(WriteWire::wire_len).sum()
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        for record in self.records {
+            w.write_wire(record)?;
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of configuring reporting for one attribute.
+#[derive(Debug)]
+pub struct AttributeStatusRecord {
+    pub status: ZclStatus,
+    pub direction: ReportingDirection,
+    pub attribute_id: u16,
+}
+
+#[derive(Debug)]
+pub struct ConfigureReportingResponse {
+    /// The attributes that couldn't be configured. Empty if all were configured successfully.
+    pub records: Vec<AttributeStatusRecord>,
+}
+
+impl Response for ConfigureReportingResponse {
+    const COMMAND_ID: u8 = 0x07;
+}
+
+impl ReadWire for ConfigureReportingResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut remaining = Vec::new();
+        r.read_to_end(&mut remaining)?;
+
+        // When every attribute was configured successfully, the response is a lone status.
+        if remaining.len() == 1 {
+            let status = Cursor::new(remaining).read_wire()?;
+            return match status {
+                ZclStatus::Success => Ok(ConfigureReportingResponse {
+                    records: Vec::new(),
+                }),
+                _ => Err(ErrorKind::ZclFailure(status).into()),
+            };
+        }
+
+        let len = remaining.len() as u64;
+        let mut r = Cursor::new(remaining);
+
+        let mut records = Vec::new();
+        while r.position() < len {
+            records.push(AttributeStatusRecord {
+                status: r.read_wire()?,
+                direction: r.read_wire()?,
+                attribute_id: r.read_wire()?,
+            });
+        }
+
+        Ok(ConfigureReportingResponse { records })
+    }
+}
+
+/// Sent by a device to report the values of attributes, e.g. as configured by
+/// `ConfigureReportingRequest`.
+#[derive(Debug)]
+pub struct ReportAttributes {
+    pub records: Vec<(u16, AttributeValue)>,
+}
+
+impl ReportAttributes {
+    pub const COMMAND_ID: u8 = 0x0A;
+}
+
+impl ReadWire for ReportAttributes {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut remaining = Vec::new();
+        r.read_to_end(&mut remaining)?;
+        let len = remaining.len() as u64;
+        let mut r = Cursor::new(remaining);
+
+        let mut records = Vec::new();
+        while r.position() < len {
+            let attribute_id = r.read_wire()?;
+            let data_type = r.read_wire()?;
+            let value = AttributeValue::read_typed(&mut r, data_type)?;
+            records.push((attribute_id, value));
+        }
+
+        Ok(ReportAttributes { records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<T: WriteWire<Error = Error>>(value: T) -> Vec<u8> {
+        let len = value.wire_len();
+        let mut buffer = Vec::new();
+        buffer.write_wire(value).unwrap();
+        assert_eq!(buffer.len(), usize::from(len));
+        buffer
+    }
+
+    #[test]
+    fn manufacturer_specific_header() {
+        let header = Header {
+            frame_control: FrameControl::CLUSTER_SPECIFIC,
+            manufacturer_code: Some(0x115F),
+            transaction_id: 0x07,
+            command_id: 0x02,
+        };
+        let bytes = encode(header);
+        assert_eq!(bytes, [0x05, 0x5F, 0x11, 0x07, 0x02]);
+
+        let header = Header::read_wire(&mut &bytes[..]).unwrap();
+        assert!(header.frame_control.is_cluster_specific());
+        assert_eq!(header.manufacturer_code, Some(0x115F));
+        assert_eq!(header.transaction_id, 0x07);
+        assert_eq!(header.command_id, 0x02);
+    }
+
+    #[test]
+    fn manufacturer_specific_bit_cleared_without_code() {
+        let header = Header {
+            frame_control: FrameControl::CLUSTER_SPECIFIC | FrameControl::MANUFACTURER_SPECIFIC,
+            manufacturer_code: None,
+            transaction_id: 0x07,
+            command_id: 0x02,
+        };
+        assert_eq!(encode(header), [0x01, 0x07, 0x02]);
+    }
+
+    #[test]
+    fn frame_control_flags() {
+        let bytes = [0x18, 0x07, 0x0B, 0x00, 0x00];
+        let frame = ZclFrame::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(frame.frame_control.direction(), Direction::ServerToClient);
+        assert!(frame.frame_control.disable_default_response());
+        assert!(!frame.frame_control.is_cluster_specific());
+        assert!(frame.is_default_response());
+
+        let bytes = [0x01, 0x07, 0x02];
+        let frame = ZclFrame::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(frame.frame_control.direction(), Direction::ClientToServer);
+        assert!(!frame.frame_control.disable_default_response());
+        assert!(frame.frame_control.is_cluster_specific());
+    }
+
+    #[test]
+    fn zcl_frame_round_trip() {
+        let frame = ZclFrame {
+            manufacturer_code: Some(0x115F),
+            transaction_seq: 0x2A,
+            ..ZclFrame::cluster_specific(0x02, vec![0xAA, 0xBB])
+        };
+        let bytes = encode(frame);
+        assert_eq!(bytes, [0x05, 0x5F, 0x11, 0x2A, 0x02, 0xAA, 0xBB]);
+
+        let frame = ZclFrame::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(frame.frame_control, FrameControl(0x05));
+        assert_eq!(frame.manufacturer_code, Some(0x115F));
+        assert_eq!(frame.transaction_seq, 0x2A);
+        assert_eq!(frame.command_id, 0x02);
+        assert_eq!(frame.payload, [0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn reported_config_with_reportable_change() {
+        // Measured Value of Temperature Measurement, every 10-300s or on a change of 0.5C.
+        let config = AttributeReportingConfig::Reported {
+            attribute_id: 0x0000,
+            data_type: 0x29,
+            min_interval: 10,
+            max_interval: 300,
+            reportable_change: Some(AttributeValue::I16(50)),
+        };
+        assert_eq!(
+            encode(config),
+            [0x00, 0x00, 0x00, 0x29, 0x0A, 0x00, 0x2C, 0x01, 0x32, 0x00]
+        );
+    }
+
+    #[test]
+    fn reported_config_without_reportable_change() {
+        // On/Off of the On/Off cluster, every 0-600s.
+        let config = AttributeReportingConfig::Reported {
+            attribute_id: 0x0000,
+            data_type: 0x10,
+            min_interval: 0,
+            max_interval: 600,
+            reportable_change: None,
+        };
+        assert_eq!(
+            encode(config),
+            [0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x58, 0x02]
+        );
+    }
+
+    #[test]
+    fn received_config() {
+        let config = AttributeReportingConfig::Received {
+            attribute_id: 0x0021,
+            timeout: 0x0E10,
+        };
+        assert_eq!(encode(config), [0x01, 0x21, 0x00, 0x10, 0x0E]);
+    }
+
+    #[test]
+    fn configure_reporting_response_success() {
+        let bytes = [0x00];
+        let resp = ConfigureReportingResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert!(resp.records.is_empty());
+    }
+
+    #[test]
+    fn configure_reporting_response_lone_failure() {
+        let bytes = [0x86];
+        match ConfigureReportingResponse::read_wire(&mut &bytes[..]) {
+            Err(Error {
+                kind: ErrorKind::ZclFailure(ZclStatus::UnsupportedAttribute),
+            }) => {}
+            result => panic!("expected ZclFailure, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn configure_reporting_response_records() {
+        let bytes = [0x86, 0x00, 0x21, 0x00, 0x8D, 0x01, 0x00, 0x00];
+        let resp = ConfigureReportingResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.records.len(), 2);
+        assert_eq!(resp.records[0].status, ZclStatus::UnsupportedAttribute);
+        assert_eq!(resp.records[0].direction, ReportingDirection::Reported);
+        assert_eq!(resp.records[0].attribute_id, 0x0021);
+        assert_eq!(resp.records[1].status, ZclStatus::Unknown(0x8D));
+        assert_eq!(resp.records[1].direction, ReportingDirection::Received);
+        assert_eq!(resp.records[1].attribute_id, 0x0000);
+    }
+
+    fn read_typed(data_type: u8, bytes: &[u8]) -> Result<AttributeValue> {
+        AttributeValue::read_typed(&mut &bytes[..], data_type)
+    }
+
+    #[test]
+    fn read_typed_values() {
+        let cases: &[(u8, &[u8], AttributeValue)] = &[
+            (0x10, &[0x01], AttributeValue::Bool(true)),
+            (0x18, &[0xA5], AttributeValue::Bitmap8(0xA5)),
+            (0x19, &[0x34, 0x12], AttributeValue::Bitmap16(0x1234)),
+            (0x20, &[0xFE], AttributeValue::U8(0xFE)),
+            (0x21, &[0x34, 0x12], AttributeValue::U16(0x1234)),
+            (
+                0x23,
+                &[0x78, 0x56, 0x34, 0x12],
+                AttributeValue::U32(0x12345678),
+            ),
+            (0x28, &[0xFE], AttributeValue::I8(-2)),
+            (0x29, &[0x0C, 0xFE], AttributeValue::I16(-500)),
+            (0x2b, &[0xFF, 0xFF, 0xFF, 0xFF], AttributeValue::I32(-1)),
+            (0x30, &[0x02], AttributeValue::Enum8(0x02)),
+            (0x31, &[0x01, 0x02], AttributeValue::Enum16(0x0201)),
+            (
+                0x41,
+                &[0x02, 0xDE, 0xAD],
+                AttributeValue::OctetString(vec![0xDE, 0xAD]),
+            ),
+            (
+                0x42,
+                &[0x04, b'T', b'R', b'A', b'D'],
+                AttributeValue::CharacterString("TRAD".to_string()),
+            ),
+        ];
+        for (data_type, bytes, expected) in cases {
+            let value = read_typed(*data_type, bytes).unwrap();
+            assert_eq!(&value, expected);
+            assert_eq!(value.data_type(), *data_type);
+            assert_eq!(usize::from(value.value_len()), bytes.len());
+        }
+    }
+
+    #[test]
+    fn read_typed_empty_string() {
+        let value = read_typed(0x42, &[0x00]).unwrap();
+        assert_eq!(value, AttributeValue::CharacterString(String::new()));
+    }
+
+    #[test]
+    fn read_typed_truncated_string() {
+        assert!(read_typed(0x42, &[0x04, b'T', b'R']).is_err());
+    }
+
+    #[test]
+    fn read_typed_unsupported() {
+        // Single precision floats aren't supported.
+        match read_typed(0x39, &[0x00, 0x00, 0x80, 0x3F]) {
+            Err(Error {
+                kind: ErrorKind::UnsupportedDataType(0x39),
+            }) => {}
+            result => panic!("expected UnsupportedDataType, got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use deconz::{ApsDataIndication, ApsReader, ClusterId, ProfileId, ReadWireExt, SourceAddress};
+use tokio::stream::Stream;
+
+use super::protocol::{AttributeValue, ReportAttributes, ZclFrame};
+use super::Result;
+
+/// The attribute values reported by a device in a single Report Attributes command.
+#[derive(Debug)]
+pub struct AttributeReport {
+    pub source_address: SourceAddress,
+    pub cluster_id: ClusterId,
+    pub records: Vec<(u16, AttributeValue)>,
+}
+
+/// Whether the indication is a ZCL Report Attributes command, which can be used to partition an
+/// `ApsReader` before calling `zcl_reports`.
+pub fn is_attribute_report(aps_data_indication: &ApsDataIndication) -> bool {
+    aps_data_indication.profile_id == ProfileId::HA
+        && matches!(read_frame(aps_data_indication), Ok(frame) if is_report_frame(&frame))
+}
+
+/// Parses the indication as a ZCL Report Attributes command. Returns `None` if it is another
+/// command, or isn't on the Home Automation profile.
+pub fn parse_attribute_report(
+    aps_data_indication: ApsDataIndication,
+) -> Result<Option<AttributeReport>> {
+    if aps_data_indication.profile_id != ProfileId::HA {
+        return Ok(None);
+    }
+
+    let frame = read_frame(&aps_data_indication)?;
+    if !is_report_frame(&frame) {
+        return Ok(None);
+    }
+
+    let report: ReportAttributes = Cursor::new(&frame.payload).read_wire()?;
+    Ok(Some(AttributeReport {
+        source_address: aps_data_indication.source_address,
+        cluster_id: aps_data_indication.cluster_id,
+        records: report.records,
+    }))
+}
+
+fn read_frame(aps_data_indication: &ApsDataIndication) -> Result<ZclFrame> {
+    Cursor::new(&aps_data_indication.asdu).read_wire()
+}
+
+fn is_report_frame(frame: &ZclFrame) -> bool {
+    !frame.frame_control.is_cluster_specific() && frame.command_id == ReportAttributes::COMMAND_ID
+}
+
+pub trait ApsReaderExt {
+    /// Turns the reader into a stream of the attribute reports it receives. Other indications,
+    /// and reports that can't be parsed, are discarded.
+    fn zcl_reports(self) -> ZclReports;
+}
+
+impl ApsReaderExt for ApsReader {
+    fn zcl_reports(self) -> ZclReports {
+        ZclReports { reader: self }
+    }
+}
+
+/// A stream of attribute reports. Created with `ApsReaderExt::zcl_reports`.
+pub struct ZclReports {
+    reader: ApsReader,
+}
+
+impl Stream for ZclReports {
+    type Item = AttributeReport;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let aps_data_indication = match Pin::new(&mut self.reader).poll_next(cx) {
+                Poll::Ready(Some(aps_data_indication)) => aps_data_indication,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match parse_attribute_report(aps_data_indication) {
+                Ok(Some(report)) => return Poll::Ready(Some(report)),
+                Ok(None) => {}
+                Err(error) => error!("zcl reports: invalid report: {}", error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deconz::{DestinationAddress, Endpoint, ExtendedAddress, ShortAddress};
+
+    use super::*;
+
+    fn indication(profile_id: ProfileId, asdu: &[u8]) -> ApsDataIndication {
+        ApsDataIndication {
+            destination_address: DestinationAddress::Nwk(ShortAddress(0x0000)),
+            destination_endpoint: Endpoint(0x01),
+            source_address: SourceAddress {
+                short: ShortAddress(0x1234),
+                extended: ExtendedAddress(0x00212effff012345),
+            },
+            source_endpoint: Endpoint(0x01),
+            profile_id,
+            cluster_id: ClusterId::TEMPERATURE_MEASUREMENT,
+            asdu: asdu.to_vec(),
+        }
+    }
+
+    #[test]
+    fn attribute_report() {
+        // Measured Value (0x0000) of 21.5C, as an int16.
+        let ind = indication(
+            ProfileId::HA,
+            &[0x18, 0x2A, 0x0A, 0x00, 0x00, 0x29, 0x66, 0x08],
+        );
+        assert!(is_attribute_report(&ind));
+
+        let report = parse_attribute_report(ind).unwrap().unwrap();
+        assert_eq!(report.source_address.short, ShortAddress(0x1234));
+        assert_eq!(report.cluster_id, ClusterId::TEMPERATURE_MEASUREMENT);
+        assert_eq!(report.records, [(0x0000, AttributeValue::I16(2150))]);
+    }
+
+    #[test]
+    fn other_commands_are_skipped() {
+        // A Read Attributes Response, and a cluster-specific command that shares the report's ID.
+        for asdu in &[&[0x18, 0x2A, 0x01][..], &[0x19, 0x2A, 0x0A]] {
+            let ind = indication(ProfileId::HA, asdu);
+            assert!(!is_attribute_report(&ind));
+            assert!(parse_attribute_report(ind).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn other_profiles_are_skipped() {
+        let ind = indication(
+            ProfileId::ZDP,
+            &[0x18, 0x2A, 0x0A, 0x00, 0x00, 0x29, 0x66, 0x08],
+        );
+        assert!(!is_attribute_report(&ind));
+        assert!(parse_attribute_report(ind).unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_report() {
+        // The value is truncated.
+        let ind = indication(ProfileId::HA, &[0x18, 0x2A, 0x0A, 0x00, 0x00, 0x29, 0x66]);
+        assert!(parse_attribute_report(ind).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use deconz::{DeviceState, Endpoint, ExtendedAddress, ShortAddress};
+use tokio::sync::watch;
+
+use super::{NodeDescriptor, SimpleDescriptor};
+
+/// Descriptors fetched recently by `Zdo`, so that repeated queries don't have to wake sleepy
+/// devices. Entries expire after the TTL. Clones share the same entries.
+#[derive(Clone)]
+pub struct DescriptorCache {
+    ttl: Duration,
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Default)]
+struct Entries {
+    active_endpoints: HashMap<ShortAddress, Cached<Vec<Endpoint>>>,
+    simple_descriptors: HashMap<(ShortAddress, Endpoint), Cached<SimpleDescriptor>>,
+    node_descriptors: HashMap<ShortAddress, Cached<NodeDescriptor>>,
+    /// The short address last known for each device, so that its descriptors can be forgotten
+    /// when it rejoins with a different one.
+    addresses: HashMap<ExtendedAddress, ShortAddress>,
+}
+
+struct Cached<T> {
+    fetched: Instant,
+    value: T,
+}
+
+impl DescriptorCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn active_endpoints(&self, addr: ShortAddress) -> Option<Vec<Endpoint>> {
+        let mut entries = self.entries.lock().expect("poisoned");
+        get(&mut entries.active_endpoints, addr, self.ttl)
+    }
+
+    pub fn insert_active_endpoints(&self, addr: ShortAddress, endpoints: Vec<Endpoint>) {
+        let mut entries = self.entries.lock().expect("poisoned");
+        entries
+            .active_endpoints
+            .insert(addr, Cached::new(endpoints));
+    }
+
+    pub fn simple_descriptor(
+        &self,
+        addr: ShortAddress,
+        endpoint: Endpoint,
+    ) -> Option<SimpleDescriptor> {
+        let mut entries = self.entries.lock().expect("poisoned");
+        get(&mut entries.simple_descriptors, (addr, endpoint), self.ttl)
+    }
+
+    pub fn insert_simple_descriptor(
+        &self,
+        addr: ShortAddress,
+        endpoint: Endpoint,
+        simple_descriptor: SimpleDescriptor,
+    ) {
+        let mut entries = self.entries.lock().expect("poisoned");
+        entries
+            .simple_descriptors
+            .insert((addr, endpoint), Cached::new(simple_descriptor));
+    }
+
+    pub fn node_descriptor(&self, addr: ShortAddress) -> Option<NodeDescriptor> {
+        let mut entries = self.entries.lock().expect("poisoned");
+        get(&mut entries.node_descriptors, addr, self.ttl)
+    }
+
+    pub fn insert_node_descriptor(&self, addr: ShortAddress, node_descriptor: NodeDescriptor) {
+        let mut entries = self.entries.lock().expect("poisoned");
+        entries
+            .node_descriptors
+            .insert(addr, Cached::new(node_descriptor));
+    }
+
+    /// Forgets the descriptors of the device at `addr`, e.g. because it has rejoined the network.
+    pub fn invalidate(&self, addr: ShortAddress) {
+        self.entries.lock().expect("poisoned").invalidate(addr);
+    }
+
+    /// Records that the device `ieee_addr` has the short address `addr`, e.g. because it resolved
+    /// to it. If the device previously had a different short address, the descriptors cached for
+    /// that address are forgotten, as another device may be given it.
+    pub fn insert_address(&self, addr: ShortAddress, ieee_addr: ExtendedAddress) {
+        let mut entries = self.entries.lock().expect("poisoned");
+        if let Some(previous) = entries.addresses.insert(ieee_addr, addr) {
+            if previous != addr {
+                entries.invalidate(previous);
+            }
+        }
+    }
+
+    /// Forgets everything cached for a device that has announced itself, having joined or
+    /// rejoined the network with `addr`. Neither the device's previous short address nor any
+    /// other device's descriptors for `addr` can be relied upon.
+    pub fn announced(&self, addr: ShortAddress, ieee_addr: ExtendedAddress) {
+        let mut entries = self.entries.lock().expect("poisoned");
+        if let Some(previous) = entries.addresses.insert(ieee_addr, addr) {
+            entries.invalidate(previous);
+        }
+        entries
+            .addresses
+            .retain(|other, other_addr| *other == ieee_addr || *other_addr != addr);
+        entries.invalidate(addr);
+    }
+
+    pub fn clear(&self) {
+        *self.entries.lock().expect("poisoned") = Entries::default();
+    }
+
+    /// Task that clears the cache whenever the adapter's network state changes, as the addresses
+    /// of devices can't be relied upon after leaving or rejoining the network.
+    pub(super) async fn task(self, mut device_state: watch::Receiver<DeviceState>) {
+        let mut network_state = None;
+        while let Some(device_state) = device_state.recv().await {
+            let previous = network_state.replace(device_state.network_state);
+            if previous.is_some() && previous != network_state {
+                debug!("network state changed: clearing descriptor cache");
+                self.clear();
+            }
+        }
+    }
+}
+
+impl Entries {
+    fn invalidate(&mut self, addr: ShortAddress) {
+        self.active_endpoints.remove(&addr);
+        self.simple_descriptors
+            .retain(|(entry_addr, _), _| *entry_addr != addr);
+        self.node_descriptors.remove(&addr);
+    }
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Self {
+            fetched: Instant::now(),
+            value,
+        }
+    }
+}
+
+/// Returns a copy of the entry for `key`, unless it has expired.
+fn get<K, V>(map: &mut HashMap<K, Cached<V>>, key: K, ttl: Duration) -> Option<V>
+where
+    K: Eq + Hash,
+    V: Clone,
+{
+    match map.get(&key) {
+        Some(cached) if cached.fetched.elapsed() < ttl => Some(cached.value.clone()),
+        Some(_) => {
+            map.remove(&key);
+            None
+        }
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60);
+    const DEVICE: ExtendedAddress = ExtendedAddress(0x00212effff012345);
+    const OTHER_DEVICE: ExtendedAddress = ExtendedAddress(0x00212effff067890);
+
+    #[test]
+    fn rejoin_forgets_previous_address() {
+        let cache = DescriptorCache::new(TTL);
+        cache.announced(ShortAddress(0x1111), DEVICE);
+        cache.insert_active_endpoints(ShortAddress(0x1111), vec![Endpoint(1)]);
+
+        cache.announced(ShortAddress(0x2222), DEVICE);
+        assert_eq!(cache.active_endpoints(ShortAddress(0x1111)), None);
+    }
+
+    #[test]
+    fn announce_forgets_reused_address() {
+        let cache = DescriptorCache::new(TTL);
+        cache.insert_address(ShortAddress(0x1111), DEVICE);
+        cache.insert_active_endpoints(ShortAddress(0x1111), vec![Endpoint(1)]);
+
+        // Another device has been given the short address.
+        cache.announced(ShortAddress(0x1111), OTHER_DEVICE);
+        assert_eq!(cache.active_endpoints(ShortAddress(0x1111)), None);
+
+        // So the first device rejoining elsewhere mustn't forget the other device's descriptors.
+        cache.insert_active_endpoints(ShortAddress(0x1111), vec![Endpoint(2)]);
+        cache.announced(ShortAddress(0x2222), DEVICE);
+        assert_eq!(
+            cache.active_endpoints(ShortAddress(0x1111)),
+            Some(vec![Endpoint(2)])
+        );
+    }
+
+    #[test]
+    fn resolved_address_forgets_previous_address() {
+        let cache = DescriptorCache::new(TTL);
+        cache.insert_address(ShortAddress(0x1111), DEVICE);
+        cache.insert_active_endpoints(ShortAddress(0x1111), vec![Endpoint(1)]);
+
+        cache.insert_address(ShortAddress(0x1111), DEVICE);
+        assert_eq!(
+            cache.active_endpoints(ShortAddress(0x1111)),
+            Some(vec![Endpoint(1)])
+        );
+
+        cache.insert_address(ShortAddress(0x2222), DEVICE);
+        assert_eq!(cache.active_endpoints(ShortAddress(0x1111)), None);
+    }
+}
@@ -1,12 +1,17 @@
 use std::fmt::{self, Display};
 use std::io;
 
+use deconz::ClusterId;
 use tokio::sync::oneshot;
 
 #[derive(Debug)]
 pub enum ErrorKind {
     Deconz(deconz::Error),
     Io(io::Error),
+    Status(u8),
+    UnexpectedCluster { expected: ClusterId, got: ClusterId },
+    PaginationStalled(u8),
+    Timeout,
     ChannelError,
 }
 
@@ -15,6 +20,18 @@ impl Display for ErrorKind {
         match self {
             ErrorKind::Deconz(error) => write!(f, "deconz: {}", error),
             ErrorKind::Io(error) => write!(f, "io: {}", error),
+            ErrorKind::Status(status) => write!(f, "unsuccessful status: {:#04x}", status),
+            ErrorKind::UnexpectedCluster { expected, got } => write!(
+                f,
+                "unexpected cluster ID in response: expected {}, got {}",
+                expected, got
+            ),
+            ErrorKind::PaginationStalled(start_index) => write!(
+                f,
+                "table request did not advance past start index {}",
+                start_index
+            ),
+            ErrorKind::Timeout => write!(f, "timeout"),
             ErrorKind::ChannelError => write!(f, "channel error"),
         }
     }
@@ -22,7 +39,26 @@ impl Display for ErrorKind {
 
 #[derive(Debug)]
 pub struct Error {
-    kind: ErrorKind,
+    pub kind: ErrorKind,
+}
+
+impl Error {
+    /// Whether the adapter failed to deliver the request in a way that resending might fix.
+    pub fn is_retryable(&self) -> bool {
+        match &self.kind {
+            ErrorKind::Deconz(deconz::Error {
+                kind: deconz::ErrorKind::ApsDeliveryFailed(status),
+                ..
+            }) => status.is_retryable(),
+            _ => false,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self { kind }
+    }
 }
 
 impl Display for Error {
@@ -57,4 +93,12 @@ impl From<oneshot::error::RecvError> for Error {
     }
 }
 
+impl From<tokio::time::Elapsed> for Error {
+    fn from(_: tokio::time::Elapsed) -> Error {
+        Error {
+            kind: ErrorKind::Timeout,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,24 +1,71 @@
+mod cache;
 mod errors;
 pub mod protocol;
+pub mod topology;
 
+use std::collections::HashSet;
+use std::convert::TryFrom;
 use std::io::Cursor;
+use std::time::Duration;
 
 use deconz::*;
 use tokio::stream::StreamExt;
 use tokio::sync::{mpsc, oneshot};
 use tophamm_helpers::{awaiting, IncrementingId};
 
-use self::protocol::{ActiveEpRequest, MgmtLqiRequest, SimpleDescRequest};
+use self::protocol::{
+    ActiveEpRequest, AddrRequestType, BindRequest, DeviceAnnce, IeeeAddrRequest, MatchDescRequest,
+    MgmtLeaveRequest, MgmtLqiRequest, MgmtPermitJoinRequest, MgmtRtgRequest, NodeDescRequest,
+    NwkAddrRequest, PowerDescRequest, SimpleDescRequest, UnbindRequest,
+};
 
-pub use self::errors::{Error, Result};
-pub use self::protocol::{Neighbor, SimpleDescriptor};
+pub use self::cache::DescriptorCache;
+pub use self::errors::{Error, ErrorKind, Result};
+pub use self::protocol::{
+    BindDestination, Neighbor, NodeDescriptor, PowerDescriptor, Route, SimpleDescriptor,
+};
 
 type TransactionId = u8;
 
+/// Wait at most this long for a response to a request, unless told otherwise.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Wait at most this long for a device to acknowledge a request to leave the network. Sleepy end
+/// devices may never respond.
+const LEAVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Short addresses from this value upwards are reserved for broadcasts.
+const BROADCAST_ADDRESSES: u16 = 0xFFF8;
+
+/// Per-request options for `Zdo::make_request_with`.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestOpts {
+    /// How long to wait for a response to each attempt.
+    pub timeout: Duration,
+    /// How many times to resend the request if the adapter fails to deliver it.
+    pub retries: u8,
+}
+
+impl Default for RequestOpts {
+    fn default() -> Self {
+        Self {
+            timeout: REQUEST_TIMEOUT,
+            retries: 0,
+        }
+    }
+}
+
 pub trait Request: WriteWire {
     const CLUSTER_ID: ClusterId;
 
     type Response: Response;
+
+    /// Fails to compile any `make_request` for a request whose response has the wrong cluster ID
+    /// (e.g. a transposed ID), rather than waiting for a response that will never match.
+    const RESPONSE_CLUSTER_MATCHES: () = assert!(
+        Self::Response::CLUSTER_ID.0 == zdo_response_cluster(Self::CLUSTER_ID).0,
+        "ZDO response cluster ID must be the request cluster ID with the high bit set"
+    );
 }
 
 pub trait Response: ReadWire {
@@ -28,27 +75,51 @@ pub trait Response: ReadWire {
 type ZdoRequest = (
     TransactionId,
     ApsDataRequest,
+    Duration,
     oneshot::Sender<Result<ApsDataIndication>>,
 );
 
 type Awaiting = awaiting::Awaiting<TransactionId, ApsDataIndication, Error>;
 
 pub struct Zdo {
+    deconz: Deconz,
     requests: mpsc::Sender<ZdoRequest>,
     transaction_ids: IncrementingId,
+    cache: Option<DescriptorCache>,
 }
 
 impl Zdo {
-    pub fn new(deconz: Deconz, aps_data_indications: mpsc::Receiver<ApsDataIndication>) -> Self {
+    pub fn new(deconz: Deconz, aps_data_indications: ApsReader) -> Self {
+        Self::spawn(deconz, aps_data_indications, None)
+    }
+
+    /// Like `new`, but descriptors are cached. The cache is invalidated for devices that rejoin
+    /// the network, under both their old and new short addresses, and cleared when the adapter's
+    /// network state changes.
+    pub fn with_cache(
+        deconz: Deconz,
+        aps_data_indications: ApsReader,
+        cache: DescriptorCache,
+    ) -> Self {
+        tokio::spawn(cache.clone().task(deconz.subscribe_device_state()));
+        Self::spawn(deconz, aps_data_indications, Some(cache))
+    }
+
+    fn spawn(
+        deconz: Deconz,
+        aps_data_indications: ApsReader,
+        cache: Option<DescriptorCache>,
+    ) -> Self {
         let (requests_tx, requests) = mpsc::channel(1);
 
         let awaiting = Awaiting::new();
         let rx = Rx {
             awaiting: awaiting.clone(),
             aps_data_indications,
+            cache: cache.clone(),
         };
         let tx = Tx {
-            deconz,
+            deconz: deconz.clone(),
             awaiting,
             requests,
         };
@@ -57,8 +128,10 @@ impl Zdo {
         tokio::spawn(tx.task());
 
         Self {
+            deconz,
             requests: requests_tx,
             transaction_ids: IncrementingId::new(),
+            cache,
         }
     }
 
@@ -73,58 +146,182 @@ impl Zdo {
         Ok(frame)
     }
 
+    fn make_aps_data_request<R>(
+        &self,
+        id: TransactionId,
+        destination: Destination,
+        request: R,
+    ) -> Result<ApsDataRequest>
+    where
+        R: Request,
+        Error: From<R::Error>,
+    {
+        let asdu = self.make_frame(id, request)?;
+        let request = ApsDataRequest::builder()
+            .destination(destination)
+            .profile(ProfileId::ZDP)
+            .cluster(R::CLUSTER_ID)
+            .source_endpoint(Endpoint(0))
+            .asdu(asdu)
+            .build()?;
+        Ok(request)
+    }
+
+    /// Send a request without waiting for a response, e.g. because it is being broadcast and
+    /// devices won't respond. Only waits until the adapter confirms the request was sent.
+    pub async fn send_request<R>(&self, destination: Destination, request: R) -> Result<()>
+    where
+        R: Request,
+        Error: From<R::Error>,
+    {
+        let id = self.transaction_ids.next();
+        let request = self.make_aps_data_request(id, destination, request)?;
+        self.deconz.aps_data_request(request).await?;
+        Ok(())
+    }
+
+    /// Send a request and wait for the response. Fails with `ErrorKind::Deconz` as soon as the
+    /// adapter reports that the request couldn't be delivered, or `ErrorKind::Timeout` if no
+    /// response arrives in time.
     pub async fn make_request<R>(&self, destination: Destination, request: R) -> Result<R::Response>
     where
         R: Request,
         Error: From<R::Error>,
         Error: From<<R::Response as ReadWire>::Error>,
     {
+        self.make_request_with(destination, request, RequestOpts::default())
+            .await
+    }
+
+    /// Like `make_request`, but fails with `ErrorKind::Timeout` if there is no response within
+    /// `timeout`, rather than the default.
+    pub async fn make_request_with_timeout<R>(
+        &self,
+        destination: Destination,
+        request: R,
+        timeout: Duration,
+    ) -> Result<R::Response>
+    where
+        R: Request,
+        Error: From<R::Error>,
+        Error: From<<R::Response as ReadWire>::Error>,
+    {
+        let opts = RequestOpts {
+            timeout,
+            ..RequestOpts::default()
+        };
+        self.make_request_with(destination, request, opts).await
+    }
+
+    /// Like `make_request`, but with a non-default timeout and number of retries.
+    pub async fn make_request_with<R>(
+        &self,
+        destination: Destination,
+        request: R,
+        opts: RequestOpts,
+    ) -> Result<R::Response>
+    where
+        R: Request,
+        Error: From<R::Error>,
+        Error: From<<R::Response as ReadWire>::Error>,
+    {
+        let () = R::RESPONSE_CLUSTER_MATCHES;
+
         let id = self.transaction_ids.next();
-        let asdu = self.make_frame(id, request)?;
-        let request = ApsDataRequest {
-            destination,
-            profile_id: ProfileId(0),
-            cluster_id: R::CLUSTER_ID,
-            source_endpoint: Endpoint(0),
-            asdu,
+        let request = self.make_aps_data_request(id, destination, request)?;
+
+        let mut retries = 0;
+        let aps_data_indication = loop {
+            match self.await_response(id, request.clone(), opts.timeout).await {
+                Err(error) if error.is_retryable() && retries < opts.retries => {
+                    retries += 1;
+                    debug!("zdo: retrying request {}: {}", id, error);
+                }
+                result => break result?,
+            }
         };
 
+        let expected = R::Response::CLUSTER_ID;
+        let got = aps_data_indication.cluster_id;
+        if got != expected {
+            return Err(ErrorKind::UnexpectedCluster { expected, got }.into());
+        }
+
+        // Skip tx_id. A truncated response will fail to parse below.
+        let asdu = aps_data_indication.asdu.get(1..).unwrap_or(&[]);
+        let mut cursor = Cursor::new(asdu);
+        let response = cursor.read_wire()?;
+
+        Ok(response)
+    }
+
+    async fn await_response(
+        &self,
+        id: TransactionId,
+        request: ApsDataRequest,
+        timeout: Duration,
+    ) -> Result<ApsDataIndication> {
         let (sender, receiver) = oneshot::channel();
         self.requests
             .clone()
-            .send((id, request, sender))
+            .send((id, request, timeout, sender))
             .await
             .unwrap();
 
-        let result = receiver.await?;
-        let aps_data_indication = result?;
-
-        // Skip tx_id
-        // TODO: assert cluster ID?
-        let mut cursor = Cursor::new(&aps_data_indication.asdu[1..]);
-        let response = cursor.read_wire()?;
-
-        Ok(response)
+        receiver.await?
     }
 }
 
 struct Rx {
     awaiting: Awaiting,
-    aps_data_indications: mpsc::Receiver<ApsDataIndication>,
+    aps_data_indications: ApsReader,
+    cache: Option<DescriptorCache>,
 }
 
 impl Rx {
     async fn task(mut self) -> Result<()> {
         while let Some(aps_data_indication) = self.aps_data_indications.next().await {
-            let id = aps_data_indication.asdu[0];
+            // Device announcements aren't responses to our requests.
+            if aps_data_indication.cluster_id == ClusterId::ZDO_DEVICE_ANNCE {
+                self.device_annce(&aps_data_indication);
+                continue;
+            }
+
+            let id = match aps_data_indication.asdu.first() {
+                Some(id) => *id,
+                None => {
+                    error!("zdo rx: empty asdu: {:?}", aps_data_indication);
+                    continue;
+                }
+            };
 
             if let Some(Ok(unsolicited)) = self.awaiting.send(&id, Ok(aps_data_indication)) {
                 error!("zdo rx: unexpected frame: {:?}", unsolicited);
             }
         }
 
+        // No more responses will arrive.
+        self.awaiting.cancel_all(|| ErrorKind::ChannelError.into());
+
         Ok(())
     }
+
+    fn device_annce(&self, aps_data_indication: &ApsDataIndication) {
+        // Skip the transaction sequence number.
+        let mut cursor = Cursor::new(aps_data_indication.asdu.get(1..).unwrap_or_default());
+        let device_annce: DeviceAnnce = match cursor.read_wire() {
+            Ok(device_annce) => device_annce,
+            Err(error) => {
+                error!("zdo rx: invalid device announcement: {}", error);
+                return;
+            }
+        };
+
+        debug!("device announced: {:?}", device_annce);
+        if let Some(cache) = &self.cache {
+            cache.announced(device_annce.network_address, device_annce.extended_address);
+        }
+    }
 }
 
 struct Tx {
@@ -135,58 +332,505 @@ struct Tx {
 
 impl Tx {
     async fn task(mut self) -> Result<()> {
-        while let Some((id, request, sender)) = self.requests.next().await {
+        while let Some((id, request, timeout, sender)) = self.requests.next().await {
+            // This fails as soon as the adapter confirms that it couldn't deliver the request,
+            // which fails the caller straight away rather than once the timeout elapses.
             let deconz = self.deconz.clone();
             let future = async move { deconz.aps_data_request(request).await };
-            tokio::spawn(self.awaiting.clone().register_while(id, sender, future));
+            tokio::spawn(self.awaiting.clone().register_while_timeout(
+                id,
+                sender,
+                future,
+                timeout,
+                ErrorKind::Timeout.into(),
+            ));
         }
 
         Ok(())
     }
 }
 
+/// Works out the `start_index` of the next page of a paginated table request. Some firmwares
+/// return empty pages, or keep returning pages without ever reaching the advertised total, so
+/// give up rather than issuing the same requests forever.
+fn next_start_index(start_index: u8, count: usize, requests: usize, total: usize) -> Result<u8> {
+    // Every page should contain at least one entry, so we should never need more requests than
+    // there are entries in the table.
+    if count == 0 || requests >= total {
+        return Err(ErrorKind::PaginationStalled(start_index).into());
+    }
+
+    u8::try_from(count)
+        .ok()
+        .and_then(|count| start_index.checked_add(count))
+        .ok_or_else(|| ErrorKind::PaginationStalled(start_index).into())
+}
+
+/// Adds a page of a neighbor table to `neighbors`, skipping any neighbors that have already been
+/// `seen`: pages may overlap if the table changes between requests. Returns whether the table is
+/// complete, in which case `neighbors` is truncated to its `total` length.
+fn add_neighbors(
+    neighbors: &mut Vec<Neighbor>,
+    seen: &mut HashSet<ExtendedAddress>,
+    page: Vec<Neighbor>,
+    total: usize,
+) -> bool {
+    neighbors.extend(
+        page.into_iter()
+            .filter(|neighbor| seen.insert(neighbor.extended_address)),
+    );
+
+    if neighbors.len() >= total {
+        neighbors.truncate(total);
+        return true;
+    }
+    false
+}
+
+/// Whether `destination` is a broadcast, either as `Destination::Broadcast` or as one of the
+/// reserved short addresses. Devices don't respond to broadcast requests.
+fn is_broadcast(destination: Destination) -> bool {
+    match destination {
+        Destination::Broadcast(_, _) => true,
+        Destination::Nwk(addr, _) => addr.0 >= BROADCAST_ADDRESSES,
+        Destination::Group(_) | Destination::Ieee(_, _) => false,
+    }
+}
+
 // Higher-level helpers. Ideally these would live on an extension trait, but async is not available
 // in traits.
 impl Zdo {
     pub async fn get_neighbors(&self, destination: Destination) -> Result<Vec<Neighbor>> {
         let mut start_index = 0;
         let mut neighbors = Vec::new();
+        let mut seen = HashSet::new();
 
-        loop {
+        for requests in 1.. {
             let resp = self
                 .make_request(destination, MgmtLqiRequest { start_index })
                 .await?;
 
             let total = resp.neighbor_table_entries as usize;
-            let count = resp.neighbor_table_list.len() as u8;
+            let count = resp.neighbor_table_list.len();
+
+            if add_neighbors(&mut neighbors, &mut seen, resp.neighbor_table_list, total) {
+                break;
+            }
+
+            start_index = next_start_index(start_index, count, requests, total)?;
+        }
+
+        Ok(neighbors)
+    }
 
-            neighbors.extend(resp.neighbor_table_list);
+    pub async fn get_routes(&self, destination: Destination) -> Result<Vec<Route>> {
+        let mut start_index = 0;
+        let mut routes = Vec::new();
+
+        for requests in 1.. {
+            let resp = self
+                .make_request(destination, MgmtRtgRequest { start_index })
+                .await?;
 
-            if neighbors.len() >= total {
-                return Ok(neighbors);
+            let total = resp.routing_table_entries as usize;
+            let count = resp.routing_table_list.len();
+
+            routes.extend(resp.routing_table_list);
+
+            if routes.len() >= total {
+                break;
             }
 
-            start_index += count;
+            start_index = next_start_index(start_index, count, requests, total)?;
         }
+
+        Ok(routes)
     }
 
     pub async fn query_endpoints(
         &self,
         addr: ShortAddress,
     ) -> Result<Vec<(Endpoint, SimpleDescriptor)>> {
+        self.query_endpoints_with(addr, false).await
+    }
+
+    /// Like `query_endpoints`, but if `refresh` is set, the descriptors are fetched from the
+    /// device even if they are cached.
+    pub async fn query_endpoints_with(
+        &self,
+        addr: ShortAddress,
+        refresh: bool,
+    ) -> Result<Vec<(Endpoint, SimpleDescriptor)>> {
+        let endpoints = self.active_endpoints(addr, refresh).await?;
+
+        let mut active_endpoints = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let simple_descriptor = self.simple_descriptor(addr, endpoint, refresh).await?;
+            active_endpoints.push((endpoint, simple_descriptor));
+        }
+
+        Ok(active_endpoints)
+    }
+
+    async fn active_endpoints(&self, addr: ShortAddress, refresh: bool) -> Result<Vec<Endpoint>> {
+        let cache = self.cache.as_ref();
+        if let Some(endpoints) = cache
+            .filter(|_| !refresh)
+            .and_then(|c| c.active_endpoints(addr))
+        {
+            return Ok(endpoints);
+        }
+
         let destination = Destination::Nwk(addr, Endpoint(0));
         let resp = self
             .make_request(destination, ActiveEpRequest { addr })
             .await?;
+        if let Some(cache) = cache {
+            cache.insert_active_endpoints(addr, resp.active_endpoints.clone());
+        }
+        Ok(resp.active_endpoints)
+    }
 
-        let mut active_endpoints = Vec::with_capacity(resp.active_endpoints.len());
-        for endpoint in resp.active_endpoints {
-            let resp = self
-                .make_request(destination, SimpleDescRequest { addr, endpoint })
-                .await?;
-            active_endpoints.push((endpoint, resp.simple_descriptor));
+    async fn simple_descriptor(
+        &self,
+        addr: ShortAddress,
+        endpoint: Endpoint,
+        refresh: bool,
+    ) -> Result<SimpleDescriptor> {
+        let cache = self.cache.as_ref();
+        if let Some(simple_descriptor) = cache
+            .filter(|_| !refresh)
+            .and_then(|c| c.simple_descriptor(addr, endpoint))
+        {
+            return Ok(simple_descriptor);
         }
 
-        Ok(active_endpoints)
+        let destination = Destination::Nwk(addr, Endpoint(0));
+        let resp = self
+            .make_request(destination, SimpleDescRequest { addr, endpoint })
+            .await?;
+        if let Some(cache) = cache {
+            cache.insert_simple_descriptor(addr, endpoint, resp.simple_descriptor.clone());
+        }
+        Ok(resp.simple_descriptor)
+    }
+
+    /// Find the endpoints on the device at `addr` that implement any of the given clusters within
+    /// `profile`.
+    pub async fn match_descriptor(
+        &self,
+        addr: ShortAddress,
+        profile: ProfileId,
+        input_clusters: Vec<ClusterId>,
+        output_clusters: Vec<ClusterId>,
+    ) -> Result<Vec<Endpoint>> {
+        let destination = Destination::Nwk(addr, Endpoint(0));
+        let request = MatchDescRequest {
+            addr,
+            profile,
+            input_clusters,
+            output_clusters,
+        };
+        let resp = self.make_request(destination, request).await?;
+        match resp.status {
+            0x00 => Ok(resp.match_list),
+            status => Err(ErrorKind::Status(status).into()),
+        }
+    }
+
+    pub async fn node_descriptor(&self, addr: ShortAddress) -> Result<NodeDescriptor> {
+        self.node_descriptor_with(addr, false).await
+    }
+
+    /// Like `node_descriptor`, but if `refresh` is set, the descriptor is fetched from the device
+    /// even if it is cached.
+    pub async fn node_descriptor_with(
+        &self,
+        addr: ShortAddress,
+        refresh: bool,
+    ) -> Result<NodeDescriptor> {
+        let cache = self.cache.as_ref();
+        if let Some(node_descriptor) = cache
+            .filter(|_| !refresh)
+            .and_then(|c| c.node_descriptor(addr))
+        {
+            return Ok(node_descriptor);
+        }
+
+        let destination = Destination::Nwk(addr, Endpoint(0));
+        let resp = self
+            .make_request(destination, NodeDescRequest { addr })
+            .await?;
+
+        let status = resp.status;
+        let node_descriptor = resp
+            .node_descriptor
+            .ok_or_else(|| Error::from(ErrorKind::Status(status)))?;
+        if let Some(cache) = cache {
+            cache.insert_node_descriptor(addr, node_descriptor.clone());
+        }
+        Ok(node_descriptor)
+    }
+
+    pub async fn power_descriptor(&self, addr: ShortAddress) -> Result<PowerDescriptor> {
+        let destination = Destination::Nwk(addr, Endpoint(0));
+        let resp = self
+            .make_request(destination, PowerDescRequest { addr })
+            .await?;
+
+        let status = resp.status;
+        resp.power_descriptor
+            .ok_or_else(|| ErrorKind::Status(status).into())
+    }
+
+    pub async fn resolve_ieee(&self, addr: ShortAddress) -> Result<ExtendedAddress> {
+        let destination = Destination::Nwk(addr, Endpoint(0));
+        let resp = self
+            .make_request(
+                destination,
+                IeeeAddrRequest {
+                    addr,
+                    request_type: AddrRequestType::Single,
+                    start_index: 0,
+                },
+            )
+            .await?;
+        // The addresses can't be trusted if the request failed, e.g. with DEVICE_NOT_FOUND.
+        if resp.status != 0x00 {
+            return Err(ErrorKind::Status(resp.status).into());
+        }
+        if let Some(cache) = &self.cache {
+            cache.insert_address(resp.nwk_addr, resp.ieee_addr);
+        }
+
+        Ok(resp.ieee_addr)
+    }
+
+    pub async fn resolve_nwk(&self, ieee_addr: ExtendedAddress) -> Result<ShortAddress> {
+        // We don't know the short address of the device, so ask all nodes with rx-on-when-idle.
+        let destination = Destination::Broadcast(BroadcastAddress::RxOnWhenIdle, Endpoint(0));
+        let resp = self
+            .make_request(
+                destination,
+                NwkAddrRequest {
+                    ieee_addr,
+                    request_type: AddrRequestType::Single,
+                    start_index: 0,
+                },
+            )
+            .await?;
+        if resp.status != 0x00 {
+            return Err(ErrorKind::Status(resp.status).into());
+        }
+        if let Some(cache) = &self.cache {
+            cache.insert_address(resp.nwk_addr, resp.ieee_addr);
+        }
+
+        Ok(resp.nwk_addr)
+    }
+
+    pub async fn leave(
+        &self,
+        destination: Destination,
+        target: ExtendedAddress,
+        remove_children: bool,
+        rejoin: bool,
+    ) -> Result<u8> {
+        let request = MgmtLeaveRequest {
+            device_address: target,
+            remove_children,
+            rejoin,
+        };
+        let resp = self
+            .make_request_with_timeout(destination, request, LEAVE_TIMEOUT)
+            .await?;
+
+        Ok(resp.status)
+    }
+
+    /// Allow devices to join the network for `seconds`. A duration of 0 closes the network, and
+    /// 0xFF leaves it open indefinitely.
+    ///
+    /// Devices don't respond to broadcast requests (e.g. to all routers), so in that
+    /// case we only wait for the request to be sent.
+    pub async fn permit_join(&self, destination: Destination, seconds: u8) -> Result<()> {
+        let request = MgmtPermitJoinRequest {
+            duration: seconds,
+            tc_significance: true,
+        };
+
+        if is_broadcast(destination) {
+            return self.send_request(destination, request).await;
+        }
+
+        let resp = self.make_request(destination, request).await?;
+        match resp.status {
+            0x00 => Ok(()),
+            status => Err(ErrorKind::Status(status).into()),
+        }
+    }
+
+    /// Ask the device at `destination` to add an entry to its binding table, so that messages
+    /// from its `src_endpoint` for `cluster_id` are sent to `target`.
+    pub async fn bind(
+        &self,
+        destination: Destination,
+        src_address: ExtendedAddress,
+        src_endpoint: Endpoint,
+        cluster_id: ClusterId,
+        target: BindDestination,
+    ) -> Result<()> {
+        let request = BindRequest {
+            src_address,
+            src_endpoint,
+            cluster_id,
+            destination: target,
+        };
+        let resp = self.make_request(destination, request).await?;
+        match resp.status {
+            0x00 => Ok(()),
+            status => Err(ErrorKind::Status(status).into()),
+        }
+    }
+
+    /// Ask the device at `destination` to remove an entry from its binding table.
+    pub async fn unbind(
+        &self,
+        destination: Destination,
+        src_address: ExtendedAddress,
+        src_endpoint: Endpoint,
+        cluster_id: ClusterId,
+        target: BindDestination,
+    ) -> Result<()> {
+        let request = UnbindRequest {
+            src_address,
+            src_endpoint,
+            cluster_id,
+            destination: target,
+        };
+        let resp = self.make_request(destination, request).await?;
+        match resp.status {
+            0x00 => Ok(()),
+            status => Err(ErrorKind::Status(status).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::{DeviceType, NeighborRelationship, PermitJoining, RxOnWhileIdle};
+    use super::*;
+
+    fn neighbor(extended_address: u64) -> Neighbor {
+        Neighbor {
+            extended_pan_id: 0x00212effff000000,
+            extended_address: ExtendedAddress(extended_address),
+            network_address: ShortAddress(extended_address as u16),
+            device_type: DeviceType::Router,
+            rx_on_while_idle: RxOnWhileIdle::On,
+            relationship: NeighborRelationship::Sibling,
+            permit_joining: PermitJoining::Accepting,
+            depth: 1,
+            link_quality_index: 255,
+        }
+    }
+
+    fn addresses(neighbors: &[Neighbor]) -> Vec<u64> {
+        neighbors
+            .iter()
+            .map(|neighbor| neighbor.extended_address.0)
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_neighbor_pages() {
+        let mut neighbors = Vec::new();
+        let mut seen = HashSet::new();
+
+        // The table changed between requests, so each page repeats the last neighbor of the one
+        // before, and the last page holds more neighbors than the advertised total.
+        let pages = vec![
+            (vec![neighbor(1), neighbor(2), neighbor(3)], 5),
+            (vec![neighbor(3), neighbor(4)], 5),
+            (vec![neighbor(4), neighbor(5), neighbor(6)], 5),
+        ];
+        let mut complete = Vec::new();
+        for (page, total) in pages {
+            complete.push(add_neighbors(&mut neighbors, &mut seen, page, total));
+        }
+
+        assert_eq!(complete, vec![false, false, true]);
+        assert_eq!(addresses(&neighbors), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn repeated_neighbor_page() {
+        let mut neighbors = Vec::new();
+        let mut seen = HashSet::new();
+
+        assert!(!add_neighbors(
+            &mut neighbors,
+            &mut seen,
+            vec![neighbor(1), neighbor(2)],
+            3
+        ));
+        assert!(!add_neighbors(
+            &mut neighbors,
+            &mut seen,
+            vec![neighbor(1), neighbor(2)],
+            3
+        ));
+        assert_eq!(addresses(&neighbors), vec![1, 2]);
+    }
+
+    fn clusters<R: Request>() -> (ClusterId, ClusterId) {
+        (R::CLUSTER_ID, R::Response::CLUSTER_ID)
+    }
+
+    #[test]
+    fn response_clusters() {
+        assert_eq!(zdo_response_cluster(ClusterId(0x0004)), ClusterId(0x8004));
+
+        for &(request, response) in &[
+            clusters::<NwkAddrRequest>(),
+            clusters::<IeeeAddrRequest>(),
+            clusters::<NodeDescRequest>(),
+            clusters::<PowerDescRequest>(),
+            clusters::<SimpleDescRequest>(),
+            clusters::<ActiveEpRequest>(),
+            clusters::<MatchDescRequest>(),
+            clusters::<BindRequest>(),
+            clusters::<UnbindRequest>(),
+            clusters::<MgmtLqiRequest>(),
+            clusters::<MgmtRtgRequest>(),
+            clusters::<MgmtLeaveRequest>(),
+            clusters::<MgmtPermitJoinRequest>(),
+        ] {
+            assert_eq!(response, zdo_response_cluster(request), "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn broadcasts() {
+        let broadcast = Destination::Broadcast(BroadcastAddress::Routers, Endpoint(0));
+        assert!(is_broadcast(broadcast));
+        assert!(is_broadcast(Destination::Nwk(
+            ShortAddress(0xFFFC),
+            Endpoint(0)
+        )));
+        assert!(is_broadcast(Destination::Nwk(
+            ShortAddress(0xFFF8),
+            Endpoint(0)
+        )));
+
+        assert!(!is_broadcast(Destination::Nwk(
+            ShortAddress(0xFFF7),
+            Endpoint(0)
+        )));
+        assert!(!is_broadcast(Destination::Nwk(
+            ShortAddress(0x0000),
+            Endpoint(0)
+        )));
+        assert!(!is_broadcast(Destination::Group(ShortAddress(0xFFFC))));
     }
 }
@@ -1,123 +1,121 @@
-use std::io::{Read, Write};
+use std::fmt::{self, Display};
+use std::io::{Cursor, Read, Write};
 
 use deconz::{
     ClusterId, Endpoint, ExtendedAddress, ProfileId, ReadWire, ReadWireExt, ShortAddress,
     WriteWire, WriteWireExt,
 };
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use super::{Error, Request, Response, Result};
+use super::{Error, ErrorKind, Request, Response, Result};
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AddrRequestType {
+    Single,
+    Extended,
+}
+
+impl WriteWire for AddrRequestType {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let byte: u8 = match self {
+            AddrRequestType::Single => 0x0,
+            AddrRequestType::Extended => 0x1,
+        };
+        w.write_wire(byte)?;
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
-pub struct SimpleDescRequest {
-    pub addr: ShortAddress,
-    pub endpoint: Endpoint,
+pub struct NwkAddrRequest {
+    pub ieee_addr: ExtendedAddress,
+    pub request_type: AddrRequestType,
+    pub start_index: u8,
 }
 
-impl Request for SimpleDescRequest {
-    const CLUSTER_ID: ClusterId = ClusterId(0x0004);
+impl Request for NwkAddrRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_NWK_ADDR_REQ;
 
-    type Response = SimpleDescResponse;
+    type Response = NwkAddrResponse;
 }
 
-impl WriteWire for SimpleDescRequest {
+impl WriteWire for NwkAddrRequest {
     type Error = Error;
 
     fn wire_len(&self) -> u16 {
-        3
+        10
     }
 
     fn write_wire<W>(self, w: &mut W) -> Result<()>
     where
         W: Write,
     {
-        w.write_wire(self.addr)?;
-        w.write_wire(self.endpoint)?;
+        w.write_wire(self.ieee_addr)?;
+        w.write_wire(self.request_type)?;
+        w.write_wire(self.start_index)?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct SimpleDescResponse {
+pub struct NwkAddrResponse {
     pub status: u8,
-    pub addr: ShortAddress,
-    pub simple_descriptor: SimpleDescriptor,
+    pub ieee_addr: ExtendedAddress,
+    pub nwk_addr: ShortAddress,
+    pub associated_devices: Option<AssociatedDevices>,
 }
 
-impl Response for SimpleDescResponse {
-    const CLUSTER_ID: ClusterId = ClusterId(0x8004);
+impl Response for NwkAddrResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_NWK_ADDR_RSP;
 }
 
-impl ReadWire for SimpleDescResponse {
+impl ReadWire for NwkAddrResponse {
     type Error = Error;
 
     fn read_wire<R>(r: &mut R) -> Result<Self>
     where
         R: Read,
     {
-        let status = r.read_wire()?;
-        let addr = r.read_wire()?;
-        let _len: u8 = r.read_wire()?;
-
-        let endpoint = r.read_wire()?;
-        let profile = r.read_wire()?;
-        let device_identifier = r.read_wire()?;
-        let device_version = r.read_wire()?;
-
-        let input_count: u8 = r.read_wire()?;
-        let mut input_clusters = Vec::with_capacity(usize::from(input_count));
-        for _ in 0..input_count {
-            input_clusters.push(r.read_wire()?);
-        }
-
-        let output_count: u8 = r.read_wire()?;
-        let mut output_clusters = Vec::with_capacity(usize::from(output_count));
-        for _ in 0..output_count {
-            output_clusters.push(r.read_wire()?);
-        }
-
-        let simple_descriptor = SimpleDescriptor {
-            endpoint,
-            profile,
-            device_identifier,
-            device_version,
-            input_clusters,
-            output_clusters,
-        };
+        let (status, ieee_addr, nwk_addr, associated_devices) = read_addr_response(r)?;
 
-        Ok(SimpleDescResponse {
+        Ok(NwkAddrResponse {
             status,
-            addr,
-            simple_descriptor,
+            ieee_addr,
+            nwk_addr,
+            associated_devices,
         })
     }
 }
 
 #[derive(Debug)]
-pub struct SimpleDescriptor {
-    pub endpoint: Endpoint,
-    pub profile: ProfileId,
-    pub device_identifier: u16,
-    pub device_version: u8, // 4 bits
-    pub input_clusters: Vec<ClusterId>,
-    pub output_clusters: Vec<ClusterId>,
-}
-
-#[derive(Debug)]
-pub struct ActiveEpRequest {
+pub struct IeeeAddrRequest {
     pub addr: ShortAddress,
+    pub request_type: AddrRequestType,
+    pub start_index: u8,
 }
 
-impl Request for ActiveEpRequest {
-    const CLUSTER_ID: ClusterId = ClusterId(0x0005);
+impl Request for IeeeAddrRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_IEEE_ADDR_REQ;
 
-    type Response = ActiveEpResponse;
+    type Response = IeeeAddrResponse;
 }
 
-impl WriteWire for ActiveEpRequest {
+impl WriteWire for IeeeAddrRequest {
     type Error = Error;
 
     fn wire_len(&self) -> u16 {
-        2
+        4
     }
 
     fn write_wire<W>(self, w: &mut W) -> Result<()>
@@ -125,85 +123,148 @@ impl WriteWire for ActiveEpRequest {
         W: Write,
     {
         w.write_wire(self.addr)?;
+        w.write_wire(self.request_type)?;
+        w.write_wire(self.start_index)?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct ActiveEpResponse {
+pub struct IeeeAddrResponse {
     pub status: u8,
-    pub addr: ShortAddress,
-    pub active_endpoints: Vec<Endpoint>,
+    pub ieee_addr: ExtendedAddress,
+    pub nwk_addr: ShortAddress,
+    pub associated_devices: Option<AssociatedDevices>,
 }
 
-impl Response for ActiveEpResponse {
-    const CLUSTER_ID: ClusterId = ClusterId(0x8005);
+impl Response for IeeeAddrResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_IEEE_ADDR_RSP;
 }
 
-impl ReadWire for ActiveEpResponse {
+impl ReadWire for IeeeAddrResponse {
     type Error = Error;
 
     fn read_wire<R>(r: &mut R) -> Result<Self>
     where
         R: Read,
     {
-        let status = r.read_wire()?;
-        let addr = r.read_wire()?;
-
-        let count: u8 = r.read_wire()?;
-        let mut active_endpoints = Vec::with_capacity(usize::from(count));
-        for _ in 0..count {
-            active_endpoints.push(r.read_wire()?);
-        }
+        let (status, ieee_addr, nwk_addr, associated_devices) = read_addr_response(r)?;
 
-        Ok(ActiveEpResponse {
+        Ok(IeeeAddrResponse {
             status,
-            addr,
-            active_endpoints,
+            ieee_addr,
+            nwk_addr,
+            associated_devices,
         })
     }
 }
 
+/// Reads the fields shared by `NwkAddrResponse` and `IeeeAddrResponse`. Devices may leave out
+/// the addresses when the request fails, so a response that is cut short is reported by its
+/// status, rather than as having run out of bytes.
+fn read_addr_response<R>(
+    r: &mut R,
+) -> Result<(u8, ExtendedAddress, ShortAddress, Option<AssociatedDevices>)>
+where
+    R: Read,
+{
+    let status: u8 = r.read_wire()?;
+    let mut read_addresses = || -> Result<_> {
+        let ieee_addr = r.read_wire()?;
+        let nwk_addr = r.read_wire()?;
+        let associated_devices = read_associated_devices(r)?;
+        Ok((ieee_addr, nwk_addr, associated_devices))
+    };
+
+    match read_addresses() {
+        Ok((ieee_addr, nwk_addr, associated_devices)) => {
+            Ok((status, ieee_addr, nwk_addr, associated_devices))
+        }
+        Err(_) if status != 0x00 => Err(ErrorKind::Status(status).into()),
+        Err(error) => Err(error),
+    }
+}
+
+/// The list of devices associated with a node, only present in a successful response to an
+/// `AddrRequestType::Extended` request.
 #[derive(Debug)]
-pub struct MgmtLqiRequest {
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AssociatedDevices {
     pub start_index: u8,
+    pub addresses: Vec<ShortAddress>,
 }
 
-impl Request for MgmtLqiRequest {
-    const CLUSTER_ID: ClusterId = ClusterId(0x0031);
+fn read_associated_devices<R>(r: &mut R) -> Result<Option<AssociatedDevices>>
+where
+    R: Read,
+{
+    let mut remaining = Vec::new();
+    r.read_to_end(&mut remaining)?;
+    if remaining.is_empty() {
+        return Ok(None);
+    }
 
-    type Response = MgmtLqiResponse;
+    let mut r = Cursor::new(remaining);
+    let count: u8 = r.read_wire()?;
+    if count == 0 {
+        // The start index and list are omitted when there are no associated devices.
+        return Ok(Some(AssociatedDevices {
+            start_index: 0,
+            addresses: Vec::new(),
+        }));
+    }
+
+    let start_index = r.read_wire()?;
+    let mut addresses = Vec::with_capacity(usize::from(count));
+    for _ in 0..count {
+        addresses.push(r.read_wire()?);
+    }
+
+    Ok(Some(AssociatedDevices {
+        start_index,
+        addresses,
+    }))
 }
 
-impl WriteWire for MgmtLqiRequest {
+#[derive(Debug)]
+pub struct NodeDescRequest {
+    pub addr: ShortAddress,
+}
+
+impl Request for NodeDescRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_NODE_DESC_REQ;
+
+    type Response = NodeDescResponse;
+}
+
+impl WriteWire for NodeDescRequest {
     type Error = Error;
 
     fn wire_len(&self) -> u16 {
-        1
+        2
     }
 
     fn write_wire<W>(self, w: &mut W) -> Result<()>
     where
         W: Write,
     {
-        w.write_wire(self.start_index)?;
+        w.write_wire(self.addr)?;
         Ok(())
     }
 }
 
 #[derive(Debug)]
-pub struct MgmtLqiResponse {
+pub struct NodeDescResponse {
     pub status: u8,
-    pub neighbor_table_entries: u8,
-    pub start_index: u8,
-    pub neighbor_table_list: Vec<Neighbor>,
+    pub addr: ShortAddress,
+    pub node_descriptor: Option<NodeDescriptor>,
 }
 
-impl Response for MgmtLqiResponse {
-    const CLUSTER_ID: ClusterId = ClusterId(0x8031);
+impl Response for NodeDescResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_NODE_DESC_RSP;
 }
 
-impl ReadWire for MgmtLqiResponse {
+impl ReadWire for NodeDescResponse {
     type Error = Error;
 
     fn read_wire<R>(r: &mut R) -> Result<Self>
@@ -211,114 +272,1310 @@ impl ReadWire for MgmtLqiResponse {
         R: Read,
     {
         let status = r.read_wire()?;
-        let neighbor_table_entries = r.read_wire()?;
-        let start_index = r.read_wire()?;
+        let addr = r.read_wire()?;
 
-        let count: u8 = r.read_wire()?;
-        let mut neighbor_table_list = Vec::with_capacity(usize::from(count));
-        for _ in 0..count {
-            let extended_pan_id = r.read_wire()?;
-            let extended_address = r.read_wire()?;
-            let network_address = r.read_wire()?;
+        // The descriptor is only included on success.
+        let node_descriptor = match status {
+            0x00 => Some(r.read_wire()?),
+            _ => None,
+        };
 
-            let byte: u8 = r.read_wire()?;
-            let device_type = match byte & 0b11 {
-                0x0 => DeviceType::Coordinator,
-                0x1 => DeviceType::Router,
-                0x2 => DeviceType::EndDevice,
-                0x3 => DeviceType::Unknown,
-                _ => unreachable!("bitfield"),
-            };
-            let rx_on_while_idle = match (byte >> 2) & 0b11 {
-                0x0 => RxOnWhileIdle::Off,
-                0x1 => RxOnWhileIdle::On,
-                0x2 => RxOnWhileIdle::Unknown,
-                0x3 => RxOnWhileIdle::Unknown, // better than panicking
-                _ => unreachable!("bitfield"),
-            };
-            let relationship = match (byte >> 4) & 0b111 {
-                0x0 => NeighborRelationship::Parent,
-                0x1 => NeighborRelationship::Child,
-                0x2 => NeighborRelationship::Sibling,
-                0x3 => NeighborRelationship::None,
-                0x4 => NeighborRelationship::PreviousChild,
-                _ => unreachable!("bitfield"),
-            };
+        Ok(NodeDescResponse {
+            status,
+            addr,
+            node_descriptor,
+        })
+    }
+}
 
-            let byte: u8 = r.read_wire()?;
-            let permit_joining = match byte & 0b11 {
-                0x0 => PermitJoining::Accepting,
-                0x1 => PermitJoining::NotAccepting,
-                0x2 => PermitJoining::Unknown,
-                0x3 => PermitJoining::Unknown, // better than panicking
-                _ => unreachable!("bitfield"),
-            };
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NodeDescriptor {
+    pub logical_type: DeviceType,
+    pub complex_descriptor_available: bool,
+    pub user_descriptor_available: bool,
+    pub frequency_band: FrequencyBand,
+    pub mac_capabilities: MacCapabilities,
+    pub manufacturer_code: u16,
+    pub max_buffer_size: u8,
+    pub max_incoming_transfer_size: u16,
+    pub server_mask: u16,
+    pub max_outgoing_transfer_size: u16,
+    pub descriptor_capabilities: u8,
+}
 
-            let depth = r.read_wire()?;
-            let link_quality_index = r.read_wire()?;
+impl ReadWire for NodeDescriptor {
+    type Error = Error;
 
-            neighbor_table_list.push(Neighbor {
-                extended_pan_id,
-                extended_address,
-                network_address,
-                device_type,
-                rx_on_while_idle,
-                relationship,
-                permit_joining,
-                depth,
-                link_quality_index,
-            });
-        }
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let byte: u8 = r.read_wire()?;
+        let logical_type = match byte & 0b111 {
+            0x0 => DeviceType::Coordinator,
+            0x1 => DeviceType::Router,
+            0x2 => DeviceType::EndDevice,
+            _ => DeviceType::Unknown,
+        };
+        let complex_descriptor_available = (byte & 0b1000) > 0;
+        let user_descriptor_available = (byte & 0b10000) > 0;
 
-        Ok(MgmtLqiResponse {
-            status,
-            neighbor_table_entries,
-            start_index,
-            neighbor_table_list,
+        let byte: u8 = r.read_wire()?;
+        let frequency_band = match byte >> 3 {
+            0b00001 => FrequencyBand::Mhz868,
+            0b00100 => FrequencyBand::Mhz902,
+            0b01000 => FrequencyBand::Mhz2400,
+            0b10000 => FrequencyBand::EuropeanSubGhz,
+            bands => FrequencyBand::Unknown(bands),
+        };
+
+        let mac_capabilities = MacCapabilities::from(u8::read_wire(r)?);
+
+        let manufacturer_code = r.read_wire()?;
+        let max_buffer_size = r.read_wire()?;
+        let max_incoming_transfer_size = r.read_wire()?;
+        let server_mask = r.read_wire()?;
+        let max_outgoing_transfer_size = r.read_wire()?;
+        let descriptor_capabilities = r.read_wire()?;
+
+        Ok(NodeDescriptor {
+            logical_type,
+            complex_descriptor_available,
+            user_descriptor_available,
+            frequency_band,
+            mac_capabilities,
+            manufacturer_code,
+            max_buffer_size,
+            max_incoming_transfer_size,
+            server_mask,
+            max_outgoing_transfer_size,
+            descriptor_capabilities,
         })
     }
 }
 
-#[derive(Debug)]
-pub enum DeviceType {
-    Coordinator,
-    Router,
-    EndDevice,
-    Unknown,
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FrequencyBand {
+    Mhz868,
+    Mhz902,
+    Mhz2400,
+    EuropeanSubGhz,
+    Unknown(u8),
 }
 
-#[derive(Debug)]
-pub enum RxOnWhileIdle {
-    Off,
-    On,
-    Unknown,
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MacCapabilities {
+    pub alternate_pan_coordinator: bool,
+    pub full_function_device: bool,
+    pub mains_powered: bool,
+    pub rx_on_when_idle: bool,
+    pub security_capable: bool,
+    pub allocate_address: bool,
 }
 
-#[derive(Debug)]
-pub enum NeighborRelationship {
-    Parent,
-    Child,
-    Sibling,
-    None,
-    PreviousChild,
+impl From<u8> for MacCapabilities {
+    fn from(byte: u8) -> Self {
+        MacCapabilities {
+            alternate_pan_coordinator: (byte & 0b1) > 0,
+            full_function_device: (byte & 0b10) > 0,
+            mains_powered: (byte & 0b100) > 0,
+            rx_on_when_idle: (byte & 0b1000) > 0,
+            security_capable: (byte & 0b100_0000) > 0,
+            allocate_address: (byte & 0b1000_0000) > 0,
+        }
+    }
 }
 
+/// Broadcast by a device when it joins or rejoins the network, possibly with a new network
+/// address.
 #[derive(Debug)]
-pub enum PermitJoining {
-    Accepting,
-    NotAccepting,
-    Unknown,
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DeviceAnnce {
+    pub network_address: ShortAddress,
+    pub extended_address: ExtendedAddress,
+    pub capabilities: MacCapabilities,
+}
+
+impl ReadWire for DeviceAnnce {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let network_address = r.read_wire()?;
+        let extended_address = r.read_wire()?;
+        let capabilities = MacCapabilities::from(u8::read_wire(r)?);
+        Ok(DeviceAnnce {
+            network_address,
+            extended_address,
+            capabilities,
+        })
+    }
 }
 
 #[derive(Debug)]
-pub struct Neighbor {
-    pub extended_pan_id: u64,
-    pub extended_address: ExtendedAddress,
-    pub network_address: ShortAddress,
-    pub device_type: DeviceType,
-    pub rx_on_while_idle: RxOnWhileIdle,
-    pub relationship: NeighborRelationship,
-    pub permit_joining: PermitJoining,
-    pub depth: u8,
-    pub link_quality_index: u8,
+pub struct PowerDescRequest {
+    pub addr: ShortAddress,
+}
+
+impl Request for PowerDescRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_POWER_DESC_REQ;
+
+    type Response = PowerDescResponse;
+}
+
+impl WriteWire for PowerDescRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.addr)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct PowerDescResponse {
+    pub status: u8,
+    pub addr: ShortAddress,
+    pub power_descriptor: Option<PowerDescriptor>,
+}
+
+impl Response for PowerDescResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_POWER_DESC_RSP;
+}
+
+impl ReadWire for PowerDescResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let addr = r.read_wire()?;
+
+        // The descriptor is only included on success.
+        let power_descriptor = match status {
+            0x00 => Some(r.read_wire()?),
+            _ => None,
+        };
+
+        Ok(PowerDescResponse {
+            status,
+            addr,
+            power_descriptor,
+        })
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PowerDescriptor {
+    pub current_power_mode: PowerMode,
+    pub available_power_sources: PowerSources,
+    pub current_power_source: PowerSources,
+    pub current_power_source_level: PowerLevel,
+}
+
+impl ReadWire for PowerDescriptor {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let byte: u8 = r.read_wire()?;
+        let current_power_mode = match byte & 0b1111 {
+            0x0 => PowerMode::RxOnWhenIdle,
+            0x1 => PowerMode::Periodic,
+            0x2 => PowerMode::Stimulated,
+            _ => PowerMode::Unknown,
+        };
+        let available_power_sources = PowerSources::from_bits(byte >> 4);
+
+        let byte: u8 = r.read_wire()?;
+        let current_power_source = PowerSources::from_bits(byte & 0b1111);
+        let current_power_source_level = match byte >> 4 {
+            0b0000 => PowerLevel::Critical,
+            0b0100 => PowerLevel::Percent33,
+            0b1000 => PowerLevel::Percent66,
+            0b1100 => PowerLevel::Percent100,
+            _ => PowerLevel::Unknown,
+        };
+
+        Ok(PowerDescriptor {
+            current_power_mode,
+            available_power_sources,
+            current_power_source,
+            current_power_source_level,
+        })
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PowerMode {
+    RxOnWhenIdle,
+    Periodic,
+    Stimulated,
+    Unknown,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PowerSources {
+    pub mains: bool,
+    pub rechargeable_battery: bool,
+    pub disposable_battery: bool,
+}
+
+impl PowerSources {
+    fn from_bits(bits: u8) -> Self {
+        PowerSources {
+            mains: (bits & 0b1) > 0,
+            rechargeable_battery: (bits & 0b10) > 0,
+            disposable_battery: (bits & 0b100) > 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PowerLevel {
+    Critical,
+    Percent33,
+    Percent66,
+    Percent100,
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct SimpleDescRequest {
+    pub addr: ShortAddress,
+    pub endpoint: Endpoint,
+}
+
+impl Request for SimpleDescRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_SIMPLE_DESC_REQ;
+
+    type Response = SimpleDescResponse;
+}
+
+impl WriteWire for SimpleDescRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        3
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.addr)?;
+        w.write_wire(self.endpoint)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SimpleDescResponse {
+    pub status: u8,
+    pub addr: ShortAddress,
+    pub simple_descriptor: SimpleDescriptor,
+}
+
+impl Response for SimpleDescResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_SIMPLE_DESC_RSP;
+}
+
+impl ReadWire for SimpleDescResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let addr = r.read_wire()?;
+        let _len: u8 = r.read_wire()?;
+
+        let endpoint = r.read_wire()?;
+        let profile = r.read_wire()?;
+        let device_identifier = r.read_wire()?;
+        let device_version = r.read_wire()?;
+
+        let input_count: u8 = r.read_wire()?;
+        let mut input_clusters = Vec::with_capacity(usize::from(input_count));
+        for _ in 0..input_count {
+            input_clusters.push(r.read_wire()?);
+        }
+
+        let output_count: u8 = r.read_wire()?;
+        let mut output_clusters = Vec::with_capacity(usize::from(output_count));
+        for _ in 0..output_count {
+            output_clusters.push(r.read_wire()?);
+        }
+
+        let simple_descriptor = SimpleDescriptor {
+            endpoint,
+            profile,
+            device_identifier,
+            device_version,
+            input_clusters,
+            output_clusters,
+        };
+
+        Ok(SimpleDescResponse {
+            status,
+            addr,
+            simple_descriptor,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SimpleDescriptor {
+    pub endpoint: Endpoint,
+    pub profile: ProfileId,
+    pub device_identifier: u16,
+    pub device_version: u8, // 4 bits
+    pub input_clusters: Vec<ClusterId>,
+    pub output_clusters: Vec<ClusterId>,
+}
+
+#[derive(Debug)]
+pub struct ActiveEpRequest {
+    pub addr: ShortAddress,
+}
+
+impl Request for ActiveEpRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_ACTIVE_EP_REQ;
+
+    type Response = ActiveEpResponse;
+}
+
+impl WriteWire for ActiveEpRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.addr)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct ActiveEpResponse {
+    pub status: u8,
+    pub addr: ShortAddress,
+    pub active_endpoints: Vec<Endpoint>,
+}
+
+impl Response for ActiveEpResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_ACTIVE_EP_RSP;
+}
+
+impl ReadWire for ActiveEpResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let addr = r.read_wire()?;
+
+        let count: u8 = r.read_wire()?;
+        let mut active_endpoints = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            active_endpoints.push(r.read_wire()?);
+        }
+
+        Ok(ActiveEpResponse {
+            status,
+            addr,
+            active_endpoints,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchDescRequest {
+    pub addr: ShortAddress,
+    pub profile: ProfileId,
+    pub input_clusters: Vec<ClusterId>,
+    pub output_clusters: Vec<ClusterId>,
+}
+
+impl Request for MatchDescRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MATCH_DESC_REQ;
+
+    type Response = MatchDescResponse;
+}
+
+impl WriteWire for MatchDescRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        let clusters = self.input_clusters.len() + self.output_clusters.len();
+        // Each cluster list is prefixed by its length.
+        2 + 2 + 1 + 1 + 2 * clusters as u16
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.addr)?;
+        w.write_wire(self.profile)?;
+
+        w.write_wire(self.input_clusters.len() as u8)?;
+        for cluster in self.input_clusters {
+            w.write_wire(cluster)?;
+        }
+
+        w.write_wire(self.output_clusters.len() as u8)?;
+        for cluster in self.output_clusters {
+            w.write_wire(cluster)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MatchDescResponse {
+    pub status: u8,
+    pub addr: ShortAddress,
+    pub match_list: Vec<Endpoint>,
+}
+
+impl Response for MatchDescResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MATCH_DESC_RSP;
+}
+
+impl ReadWire for MatchDescResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let addr = r.read_wire()?;
+
+        let count: u8 = r.read_wire()?;
+        let mut match_list = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            match_list.push(r.read_wire()?);
+        }
+
+        Ok(MatchDescResponse {
+            status,
+            addr,
+            match_list,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtLqiRequest {
+    pub start_index: u8,
+}
+
+impl Request for MgmtLqiRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_LQI_REQ;
+
+    type Response = MgmtLqiResponse;
+}
+
+impl WriteWire for MgmtLqiRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.start_index)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtLqiResponse {
+    pub status: u8,
+    pub neighbor_table_entries: u8,
+    pub start_index: u8,
+    pub neighbor_table_list: Vec<Neighbor>,
+}
+
+impl Response for MgmtLqiResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_LQI_RSP;
+}
+
+impl ReadWire for MgmtLqiResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let neighbor_table_entries = r.read_wire()?;
+        let start_index = r.read_wire()?;
+
+        let count: u8 = r.read_wire()?;
+        let mut neighbor_table_list = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let extended_pan_id = r.read_wire()?;
+            let extended_address = r.read_wire()?;
+            let network_address = r.read_wire()?;
+
+            let byte: u8 = r.read_wire()?;
+            let device_type = match byte & 0b11 {
+                0x0 => DeviceType::Coordinator,
+                0x1 => DeviceType::Router,
+                0x2 => DeviceType::EndDevice,
+                0x3 => DeviceType::Unknown,
+                _ => unreachable!("bitfield"),
+            };
+            let rx_on_while_idle = match (byte >> 2) & 0b11 {
+                0x0 => RxOnWhileIdle::Off,
+                0x1 => RxOnWhileIdle::On,
+                0x2 => RxOnWhileIdle::Unknown,
+                0x3 => RxOnWhileIdle::Unknown, // better than panicking
+                _ => unreachable!("bitfield"),
+            };
+            let relationship = match (byte >> 4) & 0b111 {
+                0x0 => NeighborRelationship::Parent,
+                0x1 => NeighborRelationship::Child,
+                0x2 => NeighborRelationship::Sibling,
+                0x3 => NeighborRelationship::None,
+                0x4 => NeighborRelationship::PreviousChild,
+                _ => unreachable!("bitfield"),
+            };
+
+            let byte: u8 = r.read_wire()?;
+            let permit_joining = match byte & 0b11 {
+                0x0 => PermitJoining::Accepting,
+                0x1 => PermitJoining::NotAccepting,
+                0x2 => PermitJoining::Unknown,
+                0x3 => PermitJoining::Unknown, // better than panicking
+                _ => unreachable!("bitfield"),
+            };
+
+            let depth = r.read_wire()?;
+            let link_quality_index = r.read_wire()?;
+
+            neighbor_table_list.push(Neighbor {
+                extended_pan_id,
+                extended_address,
+                network_address,
+                device_type,
+                rx_on_while_idle,
+                relationship,
+                permit_joining,
+                depth,
+                link_quality_index,
+            });
+        }
+
+        Ok(MgmtLqiResponse {
+            status,
+            neighbor_table_entries,
+            start_index,
+            neighbor_table_list,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DeviceType {
+    Coordinator,
+    Router,
+    EndDevice,
+    Unknown,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RxOnWhileIdle {
+    Off,
+    On,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NeighborRelationship {
+    Parent,
+    Child,
+    Sibling,
+    None,
+    PreviousChild,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PermitJoining {
+    Accepting,
+    NotAccepting,
+    Unknown,
+}
+
+impl Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceType::Coordinator => write!(f, "Coordinator"),
+            DeviceType::Router => write!(f, "Router"),
+            DeviceType::EndDevice => write!(f, "End device"),
+            DeviceType::Unknown => write!(f, "Unknown device"),
+        }
+    }
+}
+
+impl Display for RxOnWhileIdle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RxOnWhileIdle::Off => write!(f, "rx-on-while-idle: no"),
+            RxOnWhileIdle::On => write!(f, "rx-on-while-idle: yes"),
+            RxOnWhileIdle::Unknown => write!(f, "rx-on-while-idle: unknown"),
+        }
+    }
+}
+
+impl Display for NeighborRelationship {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NeighborRelationship::Parent => write!(f, "parent"),
+            NeighborRelationship::Child => write!(f, "child"),
+            NeighborRelationship::Sibling => write!(f, "sibling"),
+            NeighborRelationship::None => write!(f, "unrelated"),
+            NeighborRelationship::PreviousChild => write!(f, "previous child"),
+        }
+    }
+}
+
+impl Display for PermitJoining {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PermitJoining::Accepting => write!(f, "permit-join: yes"),
+            PermitJoining::NotAccepting => write!(f, "permit-join: no"),
+            PermitJoining::Unknown => write!(f, "permit-join: unknown"),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Neighbor {
+    pub extended_pan_id: u64,
+    pub extended_address: ExtendedAddress,
+    pub network_address: ShortAddress,
+    pub device_type: DeviceType,
+    pub rx_on_while_idle: RxOnWhileIdle,
+    pub relationship: NeighborRelationship,
+    pub permit_joining: PermitJoining,
+    pub depth: u8,
+    pub link_quality_index: u8,
+}
+
+impl Neighbor {
+    /// The link quality as a percentage of the best possible LQI. The Mgmt_Lqi response doesn't
+    /// include the RSSI, so this is the only measure of signal strength available.
+    pub fn lqi_percent(&self) -> u8 {
+        (u16::from(self.link_quality_index) * 100 / 255) as u8
+    }
+
+    /// A one-line description, e.g. "0x1234 (0x00124b0012345678): Router, child, depth 2, LQI 100%,
+    /// rx-on-while-idle: yes, permit-join: no".
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ({}): {}, {}, depth {}, LQI {}%, {}, {}",
+            self.network_address,
+            self.extended_address,
+            self.device_type,
+            self.relationship,
+            self.depth,
+            self.lqi_percent(),
+            self.rx_on_while_idle,
+            self.permit_joining,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtRtgRequest {
+    pub start_index: u8,
+}
+
+impl Request for MgmtRtgRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_RTG_REQ;
+
+    type Response = MgmtRtgResponse;
+}
+
+impl WriteWire for MgmtRtgRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        1
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.start_index)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtRtgResponse {
+    pub status: u8,
+    pub routing_table_entries: u8,
+    pub start_index: u8,
+    pub routing_table_list: Vec<Route>,
+}
+
+impl Response for MgmtRtgResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_RTG_RSP;
+}
+
+impl ReadWire for MgmtRtgResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+        let routing_table_entries = r.read_wire()?;
+        let start_index = r.read_wire()?;
+
+        let count: u8 = r.read_wire()?;
+        let mut routing_table_list = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let destination_address = r.read_wire()?;
+
+            let byte: u8 = r.read_wire()?;
+            let status = match byte & 0b111 {
+                0x0 => RouteStatus::Active,
+                0x1 => RouteStatus::DiscoveryUnderway,
+                0x2 => RouteStatus::DiscoveryFailed,
+                0x3 => RouteStatus::Inactive,
+                0x4 => RouteStatus::ValidationUnderway,
+                _ => RouteStatus::Unknown,
+            };
+            let memory_constrained = (byte & 0b1000) > 0;
+            let many_to_one = (byte & 0b10000) > 0;
+            let route_record_required = (byte & 0b100000) > 0;
+
+            let next_hop_address = r.read_wire()?;
+
+            routing_table_list.push(Route {
+                destination_address,
+                status,
+                memory_constrained,
+                many_to_one,
+                route_record_required,
+                next_hop_address,
+            });
+        }
+
+        Ok(MgmtRtgResponse {
+            status,
+            routing_table_entries,
+            start_index,
+            routing_table_list,
+        })
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RouteStatus {
+    Active,
+    DiscoveryUnderway,
+    DiscoveryFailed,
+    Inactive,
+    ValidationUnderway,
+    Unknown,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Route {
+    pub destination_address: ShortAddress,
+    pub status: RouteStatus,
+    pub memory_constrained: bool,
+    pub many_to_one: bool,
+    pub route_record_required: bool,
+    pub next_hop_address: ShortAddress,
+}
+
+#[derive(Debug)]
+pub struct MgmtLeaveRequest {
+    pub device_address: ExtendedAddress,
+    pub remove_children: bool,
+    pub rejoin: bool,
+}
+
+impl Request for MgmtLeaveRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_LEAVE_REQ;
+
+    type Response = MgmtLeaveResponse;
+}
+
+impl WriteWire for MgmtLeaveRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        9
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        let mut options: u8 = 0;
+        if self.remove_children {
+            options |= 0b0100_0000;
+        }
+        if self.rejoin {
+            options |= 0b1000_0000;
+        }
+
+        w.write_wire(self.device_address)?;
+        w.write_wire(options)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtLeaveResponse {
+    pub status: u8,
+}
+
+impl Response for MgmtLeaveResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_LEAVE_RSP;
+}
+
+impl ReadWire for MgmtLeaveResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+
+        Ok(MgmtLeaveResponse { status })
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtPermitJoinRequest {
+    pub duration: u8,
+    pub tc_significance: bool,
+}
+
+impl Request for MgmtPermitJoinRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_PERMIT_JOIN_REQ;
+
+    type Response = MgmtPermitJoinResponse;
+}
+
+impl WriteWire for MgmtPermitJoinRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        2
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.duration)?;
+        w.write_wire(self.tc_significance as u8)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct MgmtPermitJoinResponse {
+    pub status: u8,
+}
+
+impl Response for MgmtPermitJoinResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_MGMT_PERMIT_JOIN_RSP;
+}
+
+impl ReadWire for MgmtPermitJoinResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+
+        Ok(MgmtPermitJoinResponse { status })
+    }
+}
+
+/// Where a binding should send messages to: either a group, or a specific endpoint on a device.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BindDestination {
+    Group(ShortAddress),
+    Ieee(ExtendedAddress, Endpoint),
+}
+
+impl WriteWire for BindDestination {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        match self {
+            BindDestination::Group(_) => 3,
+            BindDestination::Ieee(_, _) => 10,
+        }
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        // Address mode
+        let address_mode: u8 = match self {
+            BindDestination::Group(_) => 0x1,
+            BindDestination::Ieee(_, _) => 0x3,
+        };
+        w.write_wire(address_mode)?;
+
+        // Address, and endpoint if not a group
+        match self {
+            BindDestination::Group(addr) => {
+                w.write_wire(addr)?;
+            }
+            BindDestination::Ieee(addr, endpoint) => {
+                w.write_wire(addr)?;
+                w.write_wire(endpoint)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct BindRequest {
+    pub src_address: ExtendedAddress,
+    pub src_endpoint: Endpoint,
+    pub cluster_id: ClusterId,
+    pub destination: BindDestination,
+}
+
+impl Request for BindRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_BIND_REQ;
+
+    type Response = BindResponse;
+}
+
+impl WriteWire for BindRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        11 + self.destination.wire_len()
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.src_address)?;
+        w.write_wire(self.src_endpoint)?;
+        w.write_wire(self.cluster_id)?;
+        w.write_wire(self.destination)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct BindResponse {
+    pub status: u8,
+}
+
+impl Response for BindResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_BIND_RSP;
+}
+
+impl ReadWire for BindResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+
+        Ok(BindResponse { status })
+    }
+}
+
+#[derive(Debug)]
+pub struct UnbindRequest {
+    pub src_address: ExtendedAddress,
+    pub src_endpoint: Endpoint,
+    pub cluster_id: ClusterId,
+    pub destination: BindDestination,
+}
+
+impl Request for UnbindRequest {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_UNBIND_REQ;
+
+    type Response = UnbindResponse;
+}
+
+impl WriteWire for UnbindRequest {
+    type Error = Error;
+
+    fn wire_len(&self) -> u16 {
+        11 + self.destination.wire_len()
+    }
+
+    fn write_wire<W>(self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_wire(self.src_address)?;
+        w.write_wire(self.src_endpoint)?;
+        w.write_wire(self.cluster_id)?;
+        w.write_wire(self.destination)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct UnbindResponse {
+    pub status: u8,
+}
+
+impl Response for UnbindResponse {
+    const CLUSTER_ID: ClusterId = ClusterId::ZDO_UNBIND_RSP;
+}
+
+impl ReadWire for UnbindResponse {
+    type Error = Error;
+
+    fn read_wire<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let status = r.read_wire()?;
+
+        Ok(UnbindResponse { status })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC_ADDRESS: ExtendedAddress = ExtendedAddress(0x00212effff012345);
+    const SRC_ADDRESS_BYTES: [u8; 8] = [0x45, 0x23, 0x01, 0xFF, 0xFF, 0x2E, 0x21, 0x00];
+
+    fn encode<T: WriteWire<Error = Error>>(value: T) -> Vec<u8> {
+        let len = value.wire_len();
+        let mut buffer = Vec::new();
+        buffer.write_wire(value).unwrap();
+        assert_eq!(buffer.len(), usize::from(len));
+        buffer
+    }
+
+    fn addr_response(tail: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x00];
+        bytes.extend(&SRC_ADDRESS_BYTES);
+        bytes.extend(&[0x34, 0x12]);
+        bytes.extend(tail);
+        bytes
+    }
+
+    #[test]
+    fn single_addr_response() {
+        let bytes = addr_response(&[]);
+        let resp = IeeeAddrResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.status, 0x00);
+        assert_eq!(resp.ieee_addr, SRC_ADDRESS);
+        assert_eq!(resp.nwk_addr, ShortAddress(0x1234));
+        assert!(resp.associated_devices.is_none());
+    }
+
+    #[test]
+    fn extended_addr_response() {
+        let bytes = addr_response(&[0x02, 0x05, 0x01, 0x00, 0x02, 0x00]);
+        let resp = NwkAddrResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.ieee_addr, SRC_ADDRESS);
+        assert_eq!(resp.nwk_addr, ShortAddress(0x1234));
+        let associated_devices = resp.associated_devices.unwrap();
+        assert_eq!(associated_devices.start_index, 5);
+        assert_eq!(
+            associated_devices.addresses,
+            vec![ShortAddress(0x0001), ShortAddress(0x0002)]
+        );
+    }
+
+    #[test]
+    fn extended_addr_response_without_devices() {
+        // The start index and list are left out when there are no associated devices.
+        let bytes = addr_response(&[0x00]);
+        let resp = IeeeAddrResponse::read_wire(&mut &bytes[..]).unwrap();
+        let associated_devices = resp.associated_devices.unwrap();
+        assert_eq!(associated_devices.start_index, 0);
+        assert!(associated_devices.addresses.is_empty());
+    }
+
+    #[test]
+    fn failed_addr_response() {
+        // DEVICE_NOT_FOUND, with the addresses included.
+        let mut bytes = addr_response(&[]);
+        bytes[0] = 0x81;
+        let resp = NwkAddrResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.status, 0x81);
+
+        // DEVICE_NOT_FOUND, with the addresses left out.
+        for bytes in &[&[0x81][..], &[0x81, 0x45, 0x23][..]] {
+            match IeeeAddrResponse::read_wire(&mut &bytes[..]) {
+                Err(Error {
+                    kind: ErrorKind::Status(0x81),
+                }) => {}
+                result => panic!("expected Status(0x81), got {:?}", result),
+            }
+        }
+    }
+
+    fn bind_frames(destination: BindDestination) -> (Vec<u8>, Vec<u8>) {
+        let bind = encode(BindRequest {
+            src_address: SRC_ADDRESS,
+            src_endpoint: Endpoint(1),
+            cluster_id: ClusterId::ON_OFF,
+            destination,
+        });
+        let unbind = encode(UnbindRequest {
+            src_address: SRC_ADDRESS,
+            src_endpoint: Endpoint(1),
+            cluster_id: ClusterId::ON_OFF,
+            destination,
+        });
+        (bind, unbind)
+    }
+
+    #[test]
+    fn bind_to_group() {
+        let mut expected = SRC_ADDRESS_BYTES.to_vec();
+        expected.extend(&[0x01, 0x06, 0x00, 0x01, 0x34, 0x12]);
+
+        let (bind, unbind) = bind_frames(BindDestination::Group(ShortAddress(0x1234)));
+        assert_eq!(bind, expected);
+        assert_eq!(unbind, expected);
+    }
+
+    #[test]
+    fn bind_to_endpoint() {
+        let mut expected = SRC_ADDRESS_BYTES.to_vec();
+        expected.extend(&[0x01, 0x06, 0x00, 0x03]);
+        expected.extend(&[0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x0B]);
+
+        let (bind, unbind) = bind_frames(BindDestination::Ieee(
+            ExtendedAddress(0x1122334455667788),
+            Endpoint(0x0B),
+        ));
+        assert_eq!(bind, expected);
+        assert_eq!(unbind, expected);
+    }
+
+    #[test]
+    fn node_desc_response() {
+        // From a Hue bulb: a mains-powered router on 2.4GHz, made by Philips (0x100B).
+        let bytes = [
+            0x00, 0x34, 0x12, 0x01, 0x40, 0x8E, 0x0B, 0x10, 0x52, 0x80, 0x00, 0x00, 0x2C, 0x80,
+            0x00, 0x00,
+        ];
+        let resp = NodeDescResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.status, 0x00);
+        assert_eq!(resp.addr, ShortAddress(0x1234));
+
+        let desc = resp.node_descriptor.unwrap();
+        assert_eq!(desc.logical_type, DeviceType::Router);
+        assert!(!desc.complex_descriptor_available);
+        assert!(!desc.user_descriptor_available);
+        assert!(matches!(desc.frequency_band, FrequencyBand::Mhz2400));
+        assert!(!desc.mac_capabilities.alternate_pan_coordinator);
+        assert!(desc.mac_capabilities.full_function_device);
+        assert!(desc.mac_capabilities.mains_powered);
+        assert!(desc.mac_capabilities.rx_on_when_idle);
+        assert!(!desc.mac_capabilities.security_capable);
+        assert!(desc.mac_capabilities.allocate_address);
+        assert_eq!(desc.manufacturer_code, 0x100B);
+        assert_eq!(desc.max_buffer_size, 0x52);
+        assert_eq!(desc.max_incoming_transfer_size, 0x0080);
+        assert_eq!(desc.server_mask, 0x2C00);
+        assert_eq!(desc.max_outgoing_transfer_size, 0x0080);
+        assert_eq!(desc.descriptor_capabilities, 0x00);
+    }
+
+    #[test]
+    fn failed_node_desc_response() {
+        // DEVICE_NOT_FOUND, without a descriptor.
+        let bytes = [0x81, 0x34, 0x12];
+        let resp = NodeDescResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.status, 0x81);
+        assert!(resp.node_descriptor.is_none());
+    }
+
+    #[test]
+    fn power_desc_response() {
+        // From a battery-powered sensor, which only wakes periodically.
+        let bytes = [0x00, 0x34, 0x12, 0x41, 0x84];
+        let resp = PowerDescResponse::read_wire(&mut &bytes[..]).unwrap();
+        assert_eq!(resp.status, 0x00);
+        assert_eq!(resp.addr, ShortAddress(0x1234));
+
+        let desc = resp.power_descriptor.unwrap();
+        assert!(matches!(desc.current_power_mode, PowerMode::Periodic));
+        assert!(!desc.available_power_sources.mains);
+        assert!(!desc.available_power_sources.rechargeable_battery);
+        assert!(desc.available_power_sources.disposable_battery);
+        assert!(desc.current_power_source.disposable_battery);
+        assert!(matches!(
+            desc.current_power_source_level,
+            PowerLevel::Percent66
+        ));
+    }
 }
@@ -0,0 +1,132 @@
+use std::collections::{HashSet, VecDeque};
+
+use deconz::{Destination, Endpoint, ExtendedAddress, ShortAddress};
+
+use super::protocol::{DeviceType, NeighborRelationship};
+use super::{Neighbor, Result, SimpleDescriptor, Zdo};
+
+/// The devices on the network and the links between them, as found by `Zdo::discover_network`.
+#[derive(Debug, Default)]
+pub struct Topology {
+    pub nodes: Vec<TopologyNode>,
+    pub links: Vec<TopologyLink>,
+}
+
+#[derive(Debug)]
+pub struct TopologyNode {
+    pub extended_address: ExtendedAddress,
+    pub network_address: ShortAddress,
+    pub device_type: DeviceType,
+    /// False if the node didn't respond, in which case it has no endpoints and its neighbors
+    /// are unknown.
+    pub reachable: bool,
+    /// Empty if the node's endpoints couldn't be queried.
+    pub endpoints: Vec<(Endpoint, SimpleDescriptor)>,
+}
+
+/// An entry in the neighbor table of `from`.
+#[derive(Debug)]
+pub struct TopologyLink {
+    pub from: ExtendedAddress,
+    pub to: ExtendedAddress,
+    pub relationship: NeighborRelationship,
+    pub link_quality_index: u8,
+}
+
+impl Zdo {
+    /// Walks the neighbor tables breadth-first from the coordinator, querying the endpoints of
+    /// every device found. Devices that don't respond are marked unreachable, and those whose
+    /// endpoints can't be queried are left without any, rather than failing the whole discovery.
+    pub async fn discover_network(&self) -> Result<Topology> {
+        let coordinator = ShortAddress(0x0000);
+        let extended_address = self.resolve_ieee(coordinator).await?;
+
+        let mut topology = Topology::default();
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(extended_address);
+        queue.push_back((extended_address, coordinator, DeviceType::Coordinator));
+
+        while let Some((extended_address, network_address, device_type)) = queue.pop_front() {
+            let neighbors = match self.neighbors(network_address, device_type).await {
+                Ok(neighbors) => neighbors,
+                Err(error) => {
+                    warn!(
+                        "discover_network: {:?} is unreachable: {}",
+                        network_address, error
+                    );
+                    topology.nodes.push(TopologyNode {
+                        extended_address,
+                        network_address,
+                        device_type,
+                        reachable: false,
+                        endpoints: Vec::new(),
+                    });
+                    continue;
+                }
+            };
+
+            // Carry on without the endpoints, so that the node's neighbors are still visited.
+            let (reachable, endpoints) = match self.query_endpoints(network_address).await {
+                Ok(endpoints) => (true, endpoints),
+                Err(error) => {
+                    warn!(
+                        "discover_network: failed to query endpoints of {:?}: {}",
+                        network_address, error
+                    );
+                    // We haven't heard from an end device at all, as it has no neighbor table.
+                    (device_type != DeviceType::EndDevice, Vec::new())
+                }
+            };
+
+            for neighbor in neighbors {
+                debug!(
+                    "discover_network: {:?} has neighbor {}",
+                    network_address,
+                    neighbor.summary()
+                );
+
+                // A device appears in the table of each of its neighbors, so may be found again.
+                if visited.insert(neighbor.extended_address) {
+                    queue.push_back((
+                        neighbor.extended_address,
+                        neighbor.network_address,
+                        neighbor.device_type,
+                    ));
+                }
+
+                topology.links.push(TopologyLink {
+                    from: extended_address,
+                    to: neighbor.extended_address,
+                    relationship: neighbor.relationship,
+                    link_quality_index: neighbor.link_quality_index,
+                });
+            }
+
+            topology.nodes.push(TopologyNode {
+                extended_address,
+                network_address,
+                device_type,
+                reachable,
+                endpoints,
+            });
+        }
+
+        Ok(topology)
+    }
+
+    /// Queries a node's neighbors, unless it is an end device without a neighbor table.
+    async fn neighbors(
+        &self,
+        network_address: ShortAddress,
+        device_type: DeviceType,
+    ) -> Result<Vec<Neighbor>> {
+        match device_type {
+            DeviceType::EndDevice => Ok(Vec::new()),
+            _ => {
+                let destination = Destination::Nwk(network_address, Endpoint(0));
+                self.get_neighbors(destination).await
+            }
+        }
+    }
+}